@@ -0,0 +1,481 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Shl, Shr, Sub};
+
+use openssl::bn::{BigNum, BigNumContext, MsbOption};
+
+use crate::bytes::Bytes;
+use crate::hex::Hex;
+
+pub struct BigInt(pub BigNum);
+
+fn all_digits(s: &str, is_digit: fn(&u8) -> bool) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| is_digit(&b))
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt::from_u64(0)
+    }
+
+    pub fn one() -> Self {
+        BigInt::from_u64(1)
+    }
+
+    pub fn from_u64(n: u64) -> Self {
+        BigInt(BigNum::from_slice(&n.to_be_bytes()).unwrap())
+    }
+
+    pub fn from_dec(s: &str) -> Option<Self> {
+        if !all_digits(s, |b| b.is_ascii_digit()) {
+            return None;
+        }
+        BigNum::from_dec_str(s).ok().map(BigInt)
+    }
+
+    pub fn from_hex(h: &Hex) -> Option<Self> {
+        if !all_digits(&h.0, |b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        BigNum::from_hex_str(&h.0).ok().map(BigInt)
+    }
+
+    pub fn from_bytes(b: &Bytes) -> Self {
+        BigInt(BigNum::from_slice(&b.0).unwrap())
+    }
+
+    pub fn to_hex(&self) -> Hex {
+        Hex(self.0.to_hex_str().unwrap().to_ascii_lowercase())
+    }
+
+    pub fn to_dec(&self) -> String {
+        self.0.to_dec_str().unwrap().to_string()
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes(self.0.to_vec())
+    }
+
+    pub fn to_bytes_padded(&self, len: usize) -> Option<Bytes> {
+        self.0.to_vec_padded(len as i32).ok().map(Bytes)
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        let bytes = self.0.to_vec();
+        if self.is_negative() || bytes.len() > 8 {
+            return None;
+        }
+        Some(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    pub fn bits(&self) -> usize {
+        self.0.num_bits() as usize
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.0.num_bytes() as usize
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.num_bits() == 0
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.0.is_odd()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+
+    pub fn modulo(&self, m: &BigInt) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        res.nnmod(&self.0, &m.0, &mut ctx).unwrap();
+        BigInt(res)
+    }
+
+    pub fn pow(&self, e: u32) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        res.exp(&self.0, &BigInt::from_u64(e as u64).0, &mut ctx)
+            .unwrap();
+        BigInt(res)
+    }
+
+    pub fn mod_exp(&self, e: &BigInt, m: &BigInt) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        res.mod_exp(&self.modulo(m).0, &e.0, &m.0, &mut ctx)
+            .unwrap();
+        BigInt(res)
+    }
+
+    pub fn mod_inverse(&self, m: &BigInt) -> Option<BigInt> {
+        let (g, x, _) = BigInt::egcd(&self.modulo(m), m);
+        if g == BigInt::one() {
+            Some(x.modulo(m))
+        } else {
+            None
+        }
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        res.gcd(&self.0, &other.0, &mut ctx).unwrap();
+        BigInt(res)
+    }
+
+    pub fn egcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+        let (mut old_r, mut r) = (a.clone(), b.clone());
+        let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+        let (mut old_t, mut t) = (BigInt::zero(), BigInt::one());
+        while !r.is_zero() {
+            let q = &old_r / &r;
+            let next_r = &old_r - &(&q * &r);
+            old_r = std::mem::replace(&mut r, next_r);
+            let next_s = &old_s - &(&q * &s);
+            old_s = std::mem::replace(&mut s, next_s);
+            let next_t = &old_t - &(&q * &t);
+            old_t = std::mem::replace(&mut t, next_t);
+        }
+        (old_r, old_s, old_t)
+    }
+
+    pub fn nth_root(&self, n: u32) -> BigInt {
+        assert!(n > 0 && !self.is_negative());
+        if self.is_zero() || n == 1 {
+            return self.clone();
+        }
+        let big_n = BigInt::from_u64(n as u64);
+        let n_minus_one = BigInt::from_u64(n as u64 - 1);
+        let mut x = BigInt::one() << ((self.bits() as i32 + n as i32 - 1) / n as i32);
+        loop {
+            let y = &(&(&n_minus_one * &x) + &(self / &x.pow(n - 1))) / &big_n;
+            if y >= x {
+                return x;
+            }
+            x = y;
+        }
+    }
+
+    pub fn crt(residues: &[BigInt], moduli: &[BigInt]) -> Option<BigInt> {
+        assert_eq!(residues.len(), moduli.len());
+        let product = moduli.iter().fold(BigInt::one(), |acc, m| &acc * m);
+        residues
+            .iter()
+            .zip(moduli.iter())
+            .try_fold(BigInt::zero(), |acc, (r, m)| {
+                let ms = &product / m;
+                let inv = ms.mod_inverse(m)?;
+                Some(&acc + &(&(r * &ms) * &inv))
+            })
+            .map(|x| x.modulo(&product))
+    }
+
+    pub fn random_below(n: &BigInt) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        n.0.rand_range(&mut res).unwrap();
+        BigInt(res)
+    }
+
+    pub fn random_bits(bits: usize) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        res.rand(bits as i32, MsbOption::ONE, false).unwrap();
+        BigInt(res)
+    }
+
+    pub fn random_prime(bits: usize) -> BigInt {
+        let mut res = BigNum::new().unwrap();
+        res.generate_prime(bits as i32, false, None, None).unwrap();
+        BigInt(res)
+    }
+
+    pub fn is_probable_prime(&self, rounds: usize) -> bool {
+        let two = BigInt::from_u64(2);
+        if *self < two {
+            return false;
+        }
+        for p in &[2u64, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+            let p = BigInt::from_u64(*p);
+            if *self == p {
+                return true;
+            }
+            if self.modulo(&p).is_zero() {
+                return false;
+            }
+        }
+        let n_minus_one = self - &BigInt::one();
+        let mut d = n_minus_one.clone();
+        let mut s = 0;
+        while !d.is_odd() {
+            d = &d >> 1;
+            s += 1;
+        }
+        let range = self - &BigInt::from_u64(3);
+        'witness: for _ in 0..rounds {
+            let a = &BigInt::random_below(&range) + &two;
+            let mut x = a.mod_exp(&d, self);
+            if x == BigInt::one() || x == n_minus_one {
+                continue;
+            }
+            for _ in 1..s {
+                x = x.mod_exp(&two, self);
+                if x == n_minus_one {
+                    continue 'witness;
+                }
+            }
+            return false;
+        }
+        true
+    }
+}
+
+impl Clone for BigInt {
+    fn clone(&self) -> Self {
+        BigInt(self.0.to_owned().unwrap())
+    }
+}
+
+impl PartialEq for BigInt {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for BigInt {}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BigInt({})", self.to_dec())
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_dec())
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(n: u64) -> Self {
+        BigInt::from_u64(n)
+    }
+}
+
+macro_rules! forward_binop {
+    ($t:ident, $f:ident) => {
+        impl<'a, 'b> $t<&'b BigInt> for &'a BigInt {
+            type Output = BigInt;
+
+            fn $f(self, rhs: &'b BigInt) -> BigInt {
+                BigInt($t::$f(&self.0, &rhs.0))
+            }
+        }
+
+        impl $t<BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $f(self, rhs: BigInt) -> BigInt {
+                $t::$f(&self, &rhs)
+            }
+        }
+
+        impl<'b> $t<&'b BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $f(self, rhs: &'b BigInt) -> BigInt {
+                $t::$f(&self, rhs)
+            }
+        }
+
+        impl<'a> $t<BigInt> for &'a BigInt {
+            type Output = BigInt;
+
+            fn $f(self, rhs: BigInt) -> BigInt {
+                $t::$f(self, &rhs)
+            }
+        }
+    };
+}
+
+forward_binop!(Add, add);
+forward_binop!(Sub, sub);
+forward_binop!(Mul, mul);
+forward_binop!(Div, div);
+forward_binop!(Rem, rem);
+
+impl Shl<i32> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, n: i32) -> BigInt {
+        BigInt(&self.0 << n)
+    }
+}
+
+impl Shl<i32> for BigInt {
+    type Output = BigInt;
+
+    fn shl(self, n: i32) -> BigInt {
+        &self << n
+    }
+}
+
+impl Shr<i32> for &BigInt {
+    type Output = BigInt;
+
+    fn shr(self, n: i32) -> BigInt {
+        BigInt(&self.0 >> n)
+    }
+}
+
+impl Shr<i32> for BigInt {
+    type Output = BigInt;
+
+    fn shr(self, n: i32) -> BigInt {
+        &self >> n
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt(-&self.0)
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::hex::Hex;
+
+    #[test]
+    fn test_conversions() {
+        let n = BigInt::from_hex(&Hex("0102030405060708090a".into())).unwrap();
+        assert_eq!(n.to_bytes().0, (1..=10).collect::<Vec<u8>>());
+        assert_eq!(n.to_hex().0, "0102030405060708090a");
+        assert_eq!(BigInt::from_bytes(&n.to_bytes()), n);
+        assert_eq!(n.to_bytes_padded(12).unwrap().0[..3], [0, 0, 1]);
+        assert!(n.to_bytes_padded(9).is_none());
+        assert_eq!(BigInt::from_u64(1234).to_dec(), "1234");
+        assert_eq!(BigInt::from_dec("1234").unwrap().to_u64(), Some(1234));
+        assert_eq!(BigInt::from_dec("-12").unwrap().to_dec(), "-12");
+        assert_eq!(
+            BigInt::from_hex(&Hex("-ff".into())).unwrap().to_dec(),
+            "-255"
+        );
+        ["", "-", "12zz", "0x12", " 12"].iter().for_each(|s| {
+            assert_eq!(BigInt::from_dec(s), None);
+            assert_eq!(BigInt::from_hex(&Hex(s.to_string())), None);
+        });
+        assert_eq!(BigInt::from_bytes(&Bytes(vec![])), BigInt::zero());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = BigInt::from_u64(17);
+        let b = BigInt::from_u64(5);
+        assert_eq!(&a + &b, BigInt::from_u64(22));
+        assert_eq!(&a - &b, BigInt::from_u64(12));
+        assert_eq!(&a * &b, BigInt::from_u64(85));
+        assert_eq!(&a / &b, BigInt::from_u64(3));
+        assert_eq!(&a % &b, BigInt::from_u64(2));
+        assert_eq!((-&a).modulo(&b), BigInt::from_u64(3));
+        assert_eq!(&a << 2, BigInt::from_u64(68));
+        assert_eq!(&a >> 2, BigInt::from_u64(4));
+        assert!(a > b);
+    }
+
+    #[test]
+    fn test_modular() {
+        let m = BigInt::from_u64(3233);
+        let x = BigInt::from_u64(65);
+        let c = x.mod_exp(&BigInt::from_u64(17), &m);
+        assert_eq!(c, BigInt::from_u64(2790));
+        assert_eq!(c.mod_exp(&BigInt::from_u64(413), &m), x);
+        assert_eq!(
+            BigInt::from_u64(17).mod_inverse(&BigInt::from_u64(3120)),
+            Some(BigInt::from_u64(2753))
+        );
+        assert_eq!(BigInt::from_u64(6).mod_inverse(&BigInt::from_u64(9)), None);
+        assert_eq!(
+            BigInt::from_u64(240).gcd(&BigInt::from_u64(46)),
+            BigInt::from_u64(2)
+        );
+        let (g, x, y) = BigInt::egcd(&BigInt::from_u64(240), &BigInt::from_u64(46));
+        assert_eq!(g, BigInt::from_u64(2));
+        assert_eq!(
+            &(&x * &BigInt::from_u64(240)) + &(&y * &BigInt::from_u64(46)),
+            g
+        );
+    }
+
+    #[test]
+    fn test_nth_root() {
+        let x = BigInt::random_bits(500);
+        let cube = x.pow(3);
+        assert_eq!(cube.nth_root(3), x);
+        assert_eq!((&cube + &BigInt::one()).nth_root(3), x);
+        assert_eq!((&cube - &BigInt::one()).nth_root(3), &x - &BigInt::one());
+        assert_eq!(BigInt::from_u64(99).nth_root(2), BigInt::from_u64(9));
+        assert_eq!(BigInt::from_u64(100).nth_root(2), BigInt::from_u64(10));
+        assert_eq!(BigInt::one().nth_root(5), BigInt::one());
+    }
+
+    #[test]
+    fn test_crt() {
+        let residues = [2, 3, 2]
+            .iter()
+            .map(|r| BigInt::from_u64(*r))
+            .collect::<Vec<_>>();
+        let moduli = [3, 5, 7]
+            .iter()
+            .map(|m| BigInt::from_u64(*m))
+            .collect::<Vec<_>>();
+        assert_eq!(BigInt::crt(&residues, &moduli), Some(BigInt::from_u64(23)));
+        let moduli = [3, 6, 7]
+            .iter()
+            .map(|m| BigInt::from_u64(*m))
+            .collect::<Vec<_>>();
+        assert_eq!(BigInt::crt(&residues, &moduli), None);
+    }
+
+    #[test]
+    fn test_miller_rabin() {
+        let mersenne = &(BigInt::one() << 127) - &BigInt::one();
+        assert!(mersenne.is_probable_prime(20));
+        assert!(!(&mersenne + &BigInt::from_u64(2)).is_probable_prime(20));
+        assert!(!BigInt::from_u64(561).is_probable_prime(20));
+        assert!(!BigInt::from_u64(1).is_probable_prime(20));
+        assert!(BigInt::from_u64(2).is_probable_prime(20));
+        assert!(BigInt::from_u64(41).is_probable_prime(20));
+        let p = BigInt::random_prime(256);
+        assert_eq!(p.bits(), 256);
+        assert!(p.is_probable_prime(20));
+        assert!(!(&p * &BigInt::random_prime(128)).is_probable_prime(20));
+    }
+}
@@ -4,7 +4,9 @@ use crate::base64::{encode_base64, B64};
 use crate::eng::{char_freq_score, eng_socre};
 use crate::hex::{Hex, HEX};
 use rayon::prelude::*;
+use openssl::rand::rand_bytes;
 use openssl::symm::{Crypter, Mode, Cipher};
+use rand_core::RngCore;
 
 pub const ALL_CHARS: [char; 95] = [
     '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', '0', '1', '2', '3',
@@ -21,7 +23,37 @@ pub fn edit_distance_normalized(s1: &[u8], s2: &[u8]) -> u32 {
         / s1.len() as u32
 }
 
-#[derive(Debug)]
+pub fn apply_ctr_keystream(data: &mut [u8], key: &[u8], nonce: u64, offset: usize) {
+    let cipher = Cipher::aes_128_ecb();
+    let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, None).unwrap();
+    crypter.pad(false);
+    let mut input = [0; 16];
+    let mut block = [0; 32];
+    input[..8].copy_from_slice(&nonce.to_le_bytes());
+    let mut counter = (offset / 16) as u64;
+    let mut skip = offset % 16;
+    let mut pos = 0;
+    while pos < data.len() {
+        input[8..].copy_from_slice(&counter.to_le_bytes());
+        crypter.update(&input, &mut block).unwrap();
+        let n = (16 - skip).min(data.len() - pos);
+        data[pos..pos + n]
+            .iter_mut()
+            .zip(block[skip..skip + n].iter())
+            .for_each(|(d, k)| *d ^= k);
+        pos += n;
+        skip = 0;
+        counter += 1;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EditOutOfRange {
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Bytes(pub Vec<u8>);
 
 impl Bytes {
@@ -148,6 +180,18 @@ impl Bytes {
         Bytes(s.into_bytes())
     }
 
+    pub fn random(len: usize) -> Self {
+        let mut res = vec![0; len];
+        rand_bytes(&mut res).unwrap();
+        Bytes(res)
+    }
+
+    pub fn random_with<R: RngCore>(rng: &mut R, len: usize) -> Self {
+        let mut res = vec![0; len];
+        rng.fill_bytes(&mut res);
+        Bytes(res)
+    }
+
     pub fn pad_pkcs(mut self, block_size: u8) -> Self {
         let rem = (self.0.len() % block_size as usize) as u8;
         let pad = if rem == 0 { 0 } else { block_size - rem };
@@ -157,6 +201,22 @@ impl Bytes {
         self
     }
 
+    pub fn pad_pkcs7(mut self, block_size: u8) -> Self {
+        let pad = block_size - (self.0.len() % block_size as usize) as u8;
+        self.0.resize(self.0.len() + pad as usize, pad);
+        self
+    }
+
+    pub fn unpad_pkcs7(mut self) -> Option<Self> {
+        let pad = *self.0.last()? as usize;
+        let len = self.0.len().checked_sub(pad)?;
+        if pad == 0 || self.0[len..].iter().any(|b| *b as usize != pad) {
+            return None;
+        }
+        self.0.truncate(len);
+        Some(self)
+    }
+
     pub fn encrypt_CBC(self, key: &[u8], iv: &[u8]) -> Bytes {
         let cipher = Cipher::aes_128_ecb();
         let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, Some(iv)).unwrap();
@@ -189,13 +249,48 @@ impl Bytes {
         res.truncate(count);
         Bytes(res)
     }
+
+    #[allow(non_snake_case)]
+    pub fn encrypt_CTR(mut self, key: &[u8], nonce: u64) -> Bytes {
+        apply_ctr_keystream(&mut self.0, key, nonce, 0);
+        self
+    }
+
+    #[allow(non_snake_case)]
+    pub fn decrypt_CTR(self, key: &[u8], nonce: u64) -> Bytes {
+        self.encrypt_CTR(key, nonce)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn edit_CTR(
+        &mut self,
+        key: &[u8],
+        nonce: u64,
+        offset: usize,
+        newtext: &[u8],
+    ) -> Result<(), EditOutOfRange> {
+        if offset > self.0.len() {
+            return Err(EditOutOfRange {
+                offset,
+                len: self.0.len(),
+            });
+        }
+        let end = offset + newtext.len();
+        if end > self.0.len() {
+            self.0.resize(end, 0);
+        }
+        self.0[offset..end].copy_from_slice(newtext);
+        apply_ctr_keystream(&mut self.0[offset..end], key, nonce, offset);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use openssl::symm::{decrypt, encrypt, Cipher};
 
-    use crate::bytes::Bytes;
+    use crate::base64::B64;
+    use crate::bytes::{Bytes, EditOutOfRange};
 
     #[test]
     fn test_pad_pkcs() {
@@ -217,4 +312,49 @@ mod tests {
         assert_eq!(Bytes(b"Some Crypto TextSome Crypto Text".to_vec()).encrypt_CBC(key, iv)
                        .decrypt_CBC(key, iv).into_string(), "Some Crypto TextSome Crypto Text");
     }
+
+    #[test]
+    fn test_pkcs7() {
+        let padded = Bytes(b"ICE ICE BABY\x04".to_vec()).pad_pkcs7(16);
+        assert_eq!(padded.0, b"ICE ICE BABY\x04\x03\x03\x03");
+        assert_eq!(padded.unpad_pkcs7().unwrap().0, b"ICE ICE BABY\x04");
+        let padded = Bytes(b"YELLOW SUBMARINE".to_vec()).pad_pkcs7(16);
+        assert_eq!(padded.0.len(), 32);
+        assert_eq!(padded.unpad_pkcs7().unwrap().0, b"YELLOW SUBMARINE");
+        assert_eq!(Bytes(b"ICE ICE BABY\x01\x02\x03\x04".to_vec()).unpad_pkcs7(), None);
+        assert_eq!(Bytes(b"ICE ICE BABY\x05\x05\x05\x05".to_vec()).unpad_pkcs7(), None);
+        assert_eq!(Bytes(vec![]).unpad_pkcs7(), None);
+    }
+
+    #[test]
+    fn test_ctr() {
+        let ciphertext =
+            B64("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==".into())
+                .to_bytes();
+        assert_eq!(
+            ciphertext.decrypt_CTR(b"YELLOW SUBMARINE", 0).into_string(),
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+    }
+
+    #[test]
+    fn test_edit_ctr() {
+        let key = b"YELLOW SUBMARINE";
+        let mut ciphertext = Bytes::from_string("Some Crypto TextSome Crypto Text".into())
+            .encrypt_CTR(key, 42);
+        let untouched = ciphertext.0.clone();
+        ciphertext.edit_CTR(key, 42, 13, b"Words").unwrap();
+        assert_eq!(ciphertext.0[..13], untouched[..13]);
+        assert_eq!(ciphertext.0[18..], untouched[18..]);
+        assert_eq!(
+            ciphertext.decrypt_CTR(key, 42).into_string(),
+            "Some Crypto TWordsme Crypto Text"
+        );
+        let mut ciphertext = Bytes(b"short".to_vec());
+        assert_eq!(
+            ciphertext.edit_CTR(key, 42, 6, b"gap"),
+            Err(EditOutOfRange { offset: 6, len: 5 })
+        );
+        assert_eq!(ciphertext.0, b"short");
+    }
 }
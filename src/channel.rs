@@ -0,0 +1,87 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+pub trait Channel<M> {
+    fn send(&mut self, msg: M) -> io::Result<()>;
+    fn recv(&mut self) -> io::Result<M>;
+}
+
+pub struct Endpoint<M> {
+    tx: Sender<M>,
+    rx: Receiver<M>,
+}
+
+pub fn pair<M>() -> (Endpoint<M>, Endpoint<M>) {
+    let (tx1, rx1) = mpsc::channel();
+    let (tx2, rx2) = mpsc::channel();
+    (Endpoint { tx: tx1, rx: rx2 }, Endpoint { tx: tx2, rx: rx1 })
+}
+
+impl<M> Channel<M> for Endpoint<M> {
+    fn send(&mut self, msg: M) -> io::Result<()> {
+        self.tx
+            .send(msg)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "peer hung up"))
+    }
+
+    fn recv(&mut self) -> io::Result<M> {
+        self.rx
+            .recv()
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "peer hung up"))
+    }
+}
+
+pub trait Wire: Sized {
+    fn encode(&self) -> String;
+    fn decode(line: &str) -> Option<Self>;
+}
+
+pub struct TcpChannel {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl TcpChannel {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(TcpChannel {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        })
+    }
+
+    pub fn connect(addr: SocketAddr) -> io::Result<Self> {
+        TcpChannel::new(TcpStream::connect(addr)?)
+    }
+}
+
+impl<M: Wire> Channel<M> for TcpChannel {
+    fn send(&mut self, msg: M) -> io::Result<()> {
+        let mut line = msg.encode();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())
+    }
+
+    fn recv(&mut self) -> io::Result<M> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "peer hung up"));
+        }
+        M::decode(line.trim_end())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed message"))
+    }
+}
+
+pub fn relay<M, A: Channel<M>, B: Channel<M>>(a: &mut A, b: &mut B) -> io::Result<()> {
+    loop {
+        match a.recv() {
+            Ok(msg) => b.send(msg)?,
+            Err(_) => return Ok(()),
+        }
+        match b.recv() {
+            Ok(msg) => a.send(msg)?,
+            Err(_) => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::io;
+
+use openssl::bn::BigNum;
+
+use crate::bignum::BigInt;
+use crate::bytes::Bytes;
+use crate::channel::Channel;
+use crate::hash::Hash;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhGroup {
+    pub p: BigInt,
+    pub g: BigInt,
+}
+
+impl DhGroup {
+    pub fn new(p: BigInt, g: BigInt) -> Self {
+        DhGroup { p, g }
+    }
+
+    pub fn toy() -> Self {
+        DhGroup::new(BigInt::from_u64(37), BigInt::from_u64(5))
+    }
+
+    pub fn modp_1536() -> Self {
+        DhGroup::new(
+            BigInt(BigNum::get_rfc3526_prime_1536().unwrap()),
+            BigInt::from_u64(2),
+        )
+    }
+
+    pub fn modp_2048() -> Self {
+        DhGroup::new(
+            BigInt(BigNum::get_rfc3526_prime_2048().unwrap()),
+            BigInt::from_u64(2),
+        )
+    }
+
+    pub fn modp_3072() -> Self {
+        DhGroup::new(
+            BigInt(BigNum::get_rfc3526_prime_3072().unwrap()),
+            BigInt::from_u64(2),
+        )
+    }
+
+    pub fn modp_4096() -> Self {
+        DhGroup::new(
+            BigInt(BigNum::get_rfc3526_prime_4096().unwrap()),
+            BigInt::from_u64(2),
+        )
+    }
+
+    pub fn generate_keypair(&self) -> KeyPair {
+        let private = &BigInt::random_below(&(&self.p - &BigInt::from_u64(2))) + &BigInt::one();
+        let public = self.g.mod_exp(&private, &self.p);
+        KeyPair { private, public }
+    }
+
+    pub fn shared_secret(&self, private: &BigInt, other_public: &BigInt) -> BigInt {
+        other_public.mod_exp(private, &self.p)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyPair {
+    pub private: BigInt,
+    pub public: BigInt,
+}
+
+pub fn derive_aes_key<H: Hash>(secret: &BigInt) -> Bytes {
+    let mut key = H::digest(&secret.to_bytes().0);
+    key.truncate(16);
+    Bytes(key)
+}
+
+#[derive(Debug, Clone)]
+pub enum DhMessage {
+    Negotiate { p: BigInt, g: BigInt },
+    Accept { p: BigInt, g: BigInt },
+    Public(BigInt),
+    Encrypted { ciphertext: Bytes, iv: Bytes },
+}
+
+pub fn encrypt_message(key: &Bytes, plaintext: &Bytes) -> DhMessage {
+    let iv = Bytes::random(16);
+    DhMessage::Encrypted {
+        ciphertext: plaintext.clone().pad_pkcs7(16).encrypt_CBC(&key.0, &iv.0),
+        iv,
+    }
+}
+
+pub fn decrypt_message(key: &Bytes, msg: &DhMessage) -> Option<Bytes> {
+    match msg {
+        DhMessage::Encrypted { ciphertext, iv } => {
+            ciphertext.clone().decrypt_CBC(&key.0, &iv.0).unpad_pkcs7()
+        }
+        _ => None,
+    }
+}
+
+fn unexpected(msg: &DhMessage) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message {:?}", msg),
+    )
+}
+
+pub fn run_alice<H: Hash, C: Channel<DhMessage>>(
+    chan: &mut C,
+    group: &DhGroup,
+    message: &Bytes,
+) -> io::Result<Bytes> {
+    chan.send(DhMessage::Negotiate {
+        p: group.p.clone(),
+        g: group.g.clone(),
+    })?;
+    let group = match chan.recv()? {
+        DhMessage::Accept { p, g } => DhGroup::new(p, g),
+        msg => return Err(unexpected(&msg)),
+    };
+    let keys = group.generate_keypair();
+    chan.send(DhMessage::Public(keys.public.clone()))?;
+    let other_public = match chan.recv()? {
+        DhMessage::Public(b) => b,
+        msg => return Err(unexpected(&msg)),
+    };
+    let key = derive_aes_key::<H>(&group.shared_secret(&keys.private, &other_public));
+    chan.send(encrypt_message(&key, message))?;
+    let reply = chan.recv()?;
+    decrypt_message(&key, &reply).ok_or_else(|| unexpected(&reply))
+}
+
+pub fn run_bob<H: Hash, C: Channel<DhMessage>>(chan: &mut C) -> io::Result<Bytes> {
+    let group = match chan.recv()? {
+        DhMessage::Negotiate { p, g } => DhGroup::new(p, g),
+        msg => return Err(unexpected(&msg)),
+    };
+    chan.send(DhMessage::Accept {
+        p: group.p.clone(),
+        g: group.g.clone(),
+    })?;
+    let other_public = match chan.recv()? {
+        DhMessage::Public(a) => a,
+        msg => return Err(unexpected(&msg)),
+    };
+    let keys = group.generate_keypair();
+    chan.send(DhMessage::Public(keys.public.clone()))?;
+    let key = derive_aes_key::<H>(&group.shared_secret(&keys.private, &other_public));
+    let msg = chan.recv()?;
+    let plaintext = decrypt_message(&key, &msg).ok_or_else(|| unexpected(&msg))?;
+    chan.send(encrypt_message(&key, &plaintext))?;
+    Ok(plaintext)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MitmAttack {
+    KeyFixing,
+    GeneratorOne,
+    GeneratorP,
+    GeneratorPMinusOne,
+}
+
+#[derive(Debug)]
+pub struct MitmReport {
+    pub log: Vec<String>,
+    pub plaintexts: Vec<Bytes>,
+}
+
+fn malicious_generator(attack: MitmAttack, p: &BigInt, g: BigInt) -> BigInt {
+    match attack {
+        MitmAttack::KeyFixing => g,
+        MitmAttack::GeneratorOne => BigInt::one(),
+        MitmAttack::GeneratorP => p.clone(),
+        MitmAttack::GeneratorPMinusOne => p - &BigInt::one(),
+    }
+}
+
+fn predict_secret(attack: MitmAttack, p: &BigInt, a: &BigInt, b: &BigInt) -> BigInt {
+    match attack {
+        MitmAttack::KeyFixing | MitmAttack::GeneratorP => BigInt::zero(),
+        MitmAttack::GeneratorOne => BigInt::one(),
+        MitmAttack::GeneratorPMinusOne => {
+            if *a == BigInt::one() || *b == BigInt::one() {
+                BigInt::one()
+            } else {
+                p - &BigInt::one()
+            }
+        }
+    }
+}
+
+pub fn run_mitm<H: Hash, A: Channel<DhMessage>, B: Channel<DhMessage>>(
+    alice: &mut A,
+    bob: &mut B,
+    attack: MitmAttack,
+) -> io::Result<MitmReport> {
+    let mut log = Vec::new();
+    let (p, g) = match alice.recv()? {
+        DhMessage::Negotiate { p, g } => (p, g),
+        msg => return Err(unexpected(&msg)),
+    };
+    let g = malicious_generator(attack, &p, g);
+    log.push(format!("A->M negotiate, forwarding g = {} to B", g));
+    bob.send(DhMessage::Negotiate {
+        p: p.clone(),
+        g: g.clone(),
+    })?;
+    match bob.recv()? {
+        DhMessage::Accept { .. } => {}
+        msg => return Err(unexpected(&msg)),
+    }
+    log.push(format!("B->M accept, forwarding g = {} to A", g));
+    alice.send(DhMessage::Accept {
+        p: p.clone(),
+        g: g.clone(),
+    })?;
+    let a = match alice.recv()? {
+        DhMessage::Public(a) => a,
+        msg => return Err(unexpected(&msg)),
+    };
+    let a = if attack == MitmAttack::KeyFixing {
+        log.push("A->M public key, replacing with p".to_string());
+        p.clone()
+    } else {
+        log.push(format!("A->M public key {}", a));
+        a
+    };
+    bob.send(DhMessage::Public(a.clone()))?;
+    let b = match bob.recv()? {
+        DhMessage::Public(b) => b,
+        msg => return Err(unexpected(&msg)),
+    };
+    let b = if attack == MitmAttack::KeyFixing {
+        log.push("B->M public key, replacing with p".to_string());
+        p.clone()
+    } else {
+        log.push(format!("B->M public key {}", b));
+        b
+    };
+    alice.send(DhMessage::Public(b.clone()))?;
+    let secret = predict_secret(attack, &p, &a, &b);
+    log.push(format!("predicted shared secret {}", secret));
+    let key = derive_aes_key::<H>(&secret);
+    let mut plaintexts = Vec::new();
+    let msg = alice.recv()?;
+    let plaintext = decrypt_message(&key, &msg).ok_or_else(|| unexpected(&msg))?;
+    log.push(format!("A->M {:?}", plaintext.clone().into_string()));
+    plaintexts.push(plaintext);
+    bob.send(msg)?;
+    let msg = bob.recv()?;
+    let plaintext = decrypt_message(&key, &msg).ok_or_else(|| unexpected(&msg))?;
+    log.push(format!("B->M {:?}", plaintext.clone().into_string()));
+    plaintexts.push(plaintext);
+    alice.send(msg)?;
+    Ok(MitmReport { log, plaintexts })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::channel::{pair, relay};
+    use crate::dh::{
+        decrypt_message, encrypt_message, run_alice, run_bob, run_mitm, DhGroup, MitmAttack,
+    };
+    use crate::hex::Hex;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+
+    #[test]
+    fn test_dh_toy_group() {
+        let group = DhGroup::toy();
+        let a = group.generate_keypair();
+        let b = group.generate_keypair();
+        assert_eq!(
+            group.shared_secret(&a.private, &b.public),
+            group.shared_secret(&b.private, &a.public)
+        );
+    }
+
+    #[test]
+    fn test_dh_modp_groups() {
+        assert_eq!(DhGroup::modp_1536().p, BigInt::from_hex(&Hex("ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7edee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff".into())).unwrap());
+        [
+            (DhGroup::modp_1536(), 1536),
+            (DhGroup::modp_2048(), 2048),
+            (DhGroup::modp_3072(), 3072),
+            (DhGroup::modp_4096(), 4096),
+        ]
+        .iter()
+        .for_each(|(group, bits)| {
+            assert_eq!(group.p.bits(), *bits);
+            let a = group.generate_keypair();
+            let b = group.generate_keypair();
+            assert_eq!(
+                group.shared_secret(&a.private, &b.public),
+                group.shared_secret(&b.private, &a.public)
+            );
+        });
+    }
+
+    #[test]
+    fn test_dh_protocol() {
+        let (mut alice, mut relay_a) = pair();
+        let (mut relay_b, mut bob) = pair();
+        let bob = thread::spawn(move || run_bob::<Sha256, _>(&mut bob).unwrap());
+        let relay = thread::spawn(move || relay(&mut relay_a, &mut relay_b).unwrap());
+        let message = Bytes::from_string("Some Crypto TextSome Crypto Text!".into());
+        let echoed = run_alice::<Sha256, _>(&mut alice, &DhGroup::modp_2048(), &message).unwrap();
+        drop(alice);
+        assert_eq!(echoed, message);
+        assert_eq!(bob.join().unwrap(), message);
+        relay.join().unwrap();
+    }
+
+    #[test]
+    fn test_message_ending_in_pad_byte() {
+        let key = Bytes(b"YELLOW SUBMARINE".to_vec());
+        [&b"ends in EOT\x04"[..], b"sixteen bytes\x04\x04\x04", b""]
+            .iter()
+            .for_each(|m| {
+                let message = Bytes(m.to_vec());
+                let encrypted = encrypt_message(&key, &message);
+                assert_eq!(decrypt_message(&key, &encrypted), Some(message));
+            });
+    }
+
+    #[test]
+    fn test_dh_protocol_direct() {
+        let (mut alice, mut bob) = pair();
+        let bob = thread::spawn(move || run_bob::<Sha1, _>(&mut bob).unwrap());
+        let message = Bytes::from_string("hello bob".into());
+        let echoed = run_alice::<Sha1, _>(&mut alice, &DhGroup::modp_1536(), &message).unwrap();
+        assert_eq!(echoed, message);
+        assert_eq!(bob.join().unwrap(), message);
+    }
+
+    fn mitm_scenario(attack: MitmAttack) {
+        let (mut alice, mut mitm_a) = pair();
+        let (mut mitm_b, mut bob) = pair();
+        let bob = thread::spawn(move || run_bob::<Sha1, _>(&mut bob).unwrap());
+        let mitm = thread::spawn(move || {
+            run_mitm::<Sha1, _, _>(&mut mitm_a, &mut mitm_b, attack).unwrap()
+        });
+        let message = Bytes::from_string("Attack at dawn, bring the good cheese".into());
+        let echoed = run_alice::<Sha1, _>(&mut alice, &DhGroup::modp_1536(), &message).unwrap();
+        assert_eq!(echoed, message);
+        assert_eq!(bob.join().unwrap(), message);
+        let report = mitm.join().unwrap();
+        assert_eq!(report.plaintexts, vec![message.clone(), message]);
+        assert_eq!(report.log.len(), 7);
+    }
+
+    #[test]
+    fn test_mitm_key_fixing() {
+        mitm_scenario(MitmAttack::KeyFixing);
+    }
+
+    #[test]
+    fn test_mitm_generator_one() {
+        mitm_scenario(MitmAttack::GeneratorOne);
+    }
+
+    #[test]
+    fn test_mitm_generator_p() {
+        mitm_scenario(MitmAttack::GeneratorP);
+    }
+
+    #[test]
+    fn test_mitm_generator_p_minus_one() {
+        (0..8).for_each(|_| mitm_scenario(MitmAttack::GeneratorPMinusOne));
+    }
+}
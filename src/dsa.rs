@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::bignum::BigInt;
+use crate::bytes::Bytes;
+use crate::hex::Hex;
+use crate::sha1::Sha1;
+
+const P: &str = "800000000000000089e1855218a0e7dac38136ffafa72eda7859f2171e25e65eac698c1702578b07dc2a1076da241c76c62d374d8389ea5aeffd3226a0530cc565f3bf6b50929139ebeac04f48c3c84afb796d61e5a4f9a8fda812ab59494232c7d2b4deb50aa18ee9e132bfa85ac4374d7f9091abc3d015efc871a584471bb1";
+const Q: &str = "f4f47f05794b256174bba6e9b396a7707e563c5b";
+const G: &str = "5958c9d3898b224b12672c0b98e06c60df923cb8bc999d119458fef538b8fa4046c8db53039db620c094c9fa077ef389b5322a559946a71903f990f1f7e0e025e2d7f7cf494aff1a0470f5b64c36b625a097f1651fe775323556fe00b3608c887892878480e99041be601a62166ca6894bdd41a7054ec89f756ba9fc95302291";
+
+pub fn hash_message(message: &[u8]) -> BigInt {
+    BigInt::from_bytes(&Bytes(Sha1::digest(message).to_vec()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DsaSignature {
+    pub r: BigInt,
+    pub s: BigInt,
+}
+
+#[derive(Debug, Clone)]
+pub struct DsaKeyPair {
+    pub x: BigInt,
+    pub y: BigInt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DsaChecks {
+    pub signature_range: bool,
+    pub generator: bool,
+}
+
+impl DsaChecks {
+    pub fn strict() -> Self {
+        DsaChecks {
+            signature_range: true,
+            generator: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        DsaChecks {
+            signature_range: false,
+            generator: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DsaParams {
+    pub p: BigInt,
+    pub q: BigInt,
+    pub g: BigInt,
+}
+
+impl DsaParams {
+    pub fn new(p: BigInt, q: BigInt, g: BigInt) -> Self {
+        DsaParams { p, q, g }
+    }
+
+    pub fn cryptopals() -> Self {
+        DsaParams::new(
+            BigInt::from_hex(&Hex(P.into())).unwrap(),
+            BigInt::from_hex(&Hex(Q.into())).unwrap(),
+            BigInt::from_hex(&Hex(G.into())).unwrap(),
+        )
+    }
+
+    pub fn public_key(&self, x: &BigInt) -> BigInt {
+        self.g.mod_exp(x, &self.p)
+    }
+
+    pub fn generate_keypair(&self) -> DsaKeyPair {
+        let x = &BigInt::random_below(&(&self.q - &BigInt::one())) + &BigInt::one();
+        DsaKeyPair {
+            y: self.public_key(&x),
+            x,
+        }
+    }
+
+    pub fn sign_with_k(&self, x: &BigInt, h: &BigInt, k: &BigInt) -> Option<DsaSignature> {
+        let r = self.g.mod_exp(k, &self.p).modulo(&self.q);
+        let s = (&k.mod_inverse(&self.q)? * &(h + &(x * &r))).modulo(&self.q);
+        if r.is_zero() || s.is_zero() {
+            return None;
+        }
+        Some(DsaSignature { r, s })
+    }
+
+    pub fn sign(&self, x: &BigInt, message: &[u8]) -> DsaSignature {
+        let h = hash_message(message);
+        loop {
+            let k = &BigInt::random_below(&(&self.q - &BigInt::one())) + &BigInt::one();
+            if let Some(sig) = self.sign_with_k(x, &h, &k) {
+                return sig;
+            }
+        }
+    }
+
+    pub fn has_valid_generator(&self) -> bool {
+        BigInt::one() < self.g
+            && self.g < self.p
+            && self.g.mod_exp(&self.q, &self.p) == BigInt::one()
+    }
+
+    pub fn verify_hash(&self, y: &BigInt, h: &BigInt, sig: &DsaSignature) -> bool {
+        self.verify_hash_with(y, h, sig, DsaChecks::strict())
+    }
+
+    pub fn verify_hash_with(
+        &self,
+        y: &BigInt,
+        h: &BigInt,
+        sig: &DsaSignature,
+        checks: DsaChecks,
+    ) -> bool {
+        let zero = BigInt::zero();
+        if checks.signature_range
+            && (sig.r <= zero || sig.r >= self.q || sig.s <= zero || sig.s >= self.q)
+        {
+            return false;
+        }
+        if checks.generator && !self.has_valid_generator() {
+            return false;
+        }
+        let w = match sig.s.mod_inverse(&self.q) {
+            Some(w) => w,
+            None => return false,
+        };
+        let u1 = (h * &w).modulo(&self.q);
+        let u2 = (&sig.r * &w).modulo(&self.q);
+        let v = (&self.g.mod_exp(&u1, &self.p) * &y.mod_exp(&u2, &self.p))
+            .modulo(&self.p)
+            .modulo(&self.q);
+        v == sig.r
+    }
+
+    pub fn verify(&self, y: &BigInt, message: &[u8], sig: &DsaSignature) -> bool {
+        self.verify_hash(y, &hash_message(message), sig)
+    }
+
+    pub fn verify_with(
+        &self,
+        y: &BigInt,
+        message: &[u8],
+        sig: &DsaSignature,
+        checks: DsaChecks,
+    ) -> bool {
+        self.verify_hash_with(y, &hash_message(message), sig, checks)
+    }
+
+    pub fn magic_signature(&self, y: &BigInt) -> Option<DsaSignature> {
+        let g = self.g.modulo(&self.p);
+        if g.is_zero() {
+            return Some(DsaSignature {
+                r: BigInt::zero(),
+                s: &BigInt::random_below(&(&self.q - &BigInt::one())) + &BigInt::one(),
+            });
+        }
+        if g != BigInt::one() {
+            return None;
+        }
+        loop {
+            let z = &BigInt::random_below(&(&self.q - &BigInt::one())) + &BigInt::one();
+            let r = y.mod_exp(&z, &self.p).modulo(&self.q);
+            let s = (&r * &z.mod_inverse(&self.q)?).modulo(&self.q);
+            if !r.is_zero() && !s.is_zero() {
+                return Some(DsaSignature { r, s });
+            }
+        }
+    }
+
+    pub fn x_from_k(&self, h: &BigInt, sig: &DsaSignature, k: &BigInt) -> Option<BigInt> {
+        let r_inv = sig.r.mod_inverse(&self.q)?;
+        Some((&(&(&sig.s * k) - h) * &r_inv).modulo(&self.q))
+    }
+
+    pub fn brute_force_k(
+        &self,
+        y: &BigInt,
+        h: &BigInt,
+        sig: &DsaSignature,
+        range: Range<u32>,
+    ) -> Option<(BigInt, BigInt)> {
+        let mut gk = self
+            .g
+            .mod_exp(&BigInt::from_u64(range.start as u64), &self.p);
+        for k in range {
+            if gk.modulo(&self.q) == sig.r {
+                let k = BigInt::from_u64(k as u64);
+                if let Some(x) = self.x_from_k(h, sig, &k) {
+                    if self.public_key(&x) == *y {
+                        return Some((k, x));
+                    }
+                }
+            }
+            gk = (&gk * &self.g).modulo(&self.p);
+        }
+        None
+    }
+
+    pub fn find_repeated_k(
+        &self,
+        y: &BigInt,
+        messages: &[SignedMessage],
+    ) -> Option<(BigInt, BigInt)> {
+        let mut by_r: HashMap<String, &SignedMessage> = HashMap::new();
+        for m2 in messages {
+            let m1 = match by_r.insert(m2.sig.r.to_hex().0, m2) {
+                Some(m1) if m1.sig.s != m2.sig.s => m1,
+                _ => continue,
+            };
+            let ds = (&m1.sig.s - &m2.sig.s).modulo(&self.q);
+            let dm = (&m1.h - &m2.h).modulo(&self.q);
+            let k = (&dm * &ds.mod_inverse(&self.q)?).modulo(&self.q);
+            if let Some(x) = self.x_from_k(&m1.h, &m1.sig, &k) {
+                if self.public_key(&x) == *y {
+                    return Some((k, x));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Default for DsaParams {
+    fn default() -> Self {
+        DsaParams::cryptopals()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedMessage {
+    pub msg: String,
+    pub sig: DsaSignature,
+    pub h: BigInt,
+}
+
+fn unescape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('\\', Some('n')) => {
+                res.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                res.push('\\');
+                chars.next();
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+pub fn parse_records<P: AsRef<Path>>(path: P) -> io::Result<Vec<HashMap<String, String>>> {
+    let mut records = vec![];
+    let mut current = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let (key, value) = match line.find(": ") {
+            Some(i) => (line[..i].to_string(), unescape(&line[i + 2..])),
+            None => continue,
+        };
+        if current.contains_key(&key) {
+            records.push(std::mem::take(&mut current));
+        }
+        current.insert(key, value);
+    }
+    if !current.is_empty() {
+        records.push(current);
+    }
+    Ok(records)
+}
+
+impl SignedMessage {
+    pub fn from_record(record: &HashMap<String, String>) -> Option<Self> {
+        let dec = |key: &str| BigInt::from_dec(record.get(key)?);
+        Some(SignedMessage {
+            msg: record.get("msg").cloned().unwrap_or_default(),
+            sig: DsaSignature {
+                r: dec("r")?,
+                s: dec("s")?,
+            },
+            h: BigInt::from_hex(&Hex(record.get("m")?.clone()))?,
+        })
+    }
+}
+
+pub fn load_signed_messages<P: AsRef<Path>>(path: P) -> io::Result<Vec<SignedMessage>> {
+    parse_records(path)?
+        .iter()
+        .map(|r| {
+            SignedMessage::from_record(r)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad signed message"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::dsa::{hash_message, load_signed_messages, parse_records, DsaChecks, DsaParams};
+    use crate::hex::Hex;
+    use crate::sha1::Sha1;
+
+    fn fingerprint(x: &BigInt) -> String {
+        Bytes(Sha1::digest(x.to_hex().0.as_bytes()).to_vec())
+            .to_hex()
+            .0
+    }
+
+    #[test]
+    fn test_dsa_sign_verify() {
+        let params = DsaParams::cryptopals();
+        let keys = params.generate_keypair();
+        let sig = params.sign(&keys.x, b"hi mom");
+        assert!(params.verify(&keys.y, b"hi mom", &sig));
+        assert!(!params.verify(&keys.y, b"hi dad", &sig));
+        assert!(!params.verify(&params.generate_keypair().y, b"hi mom", &sig));
+    }
+
+    #[test]
+    fn test_dsa_x_from_k() {
+        let params = DsaParams::cryptopals();
+        let keys = params.generate_keypair();
+        let h = hash_message(b"hi mom");
+        let k = BigInt::from_u64(0xdead_beef);
+        let sig = params.sign_with_k(&keys.x, &h, &k).unwrap();
+        assert_eq!(params.x_from_k(&h, &sig, &k), Some(keys.x));
+    }
+
+    #[test]
+    fn test_dsa_brute_force_k() {
+        let params = DsaParams::cryptopals();
+        let record = &parse_records("resources/chal43").unwrap()[0];
+        let y = BigInt::from_hex(&Hex(record["y"].clone())).unwrap();
+        let signed = &load_signed_messages("resources/chal43").unwrap()[0];
+        assert_eq!(
+            signed.msg,
+            "For those that envy a MC it can be hazardous to your health\n\
+             So be friendly, a matter of life and death, just like a etch-a-sketch\n"
+        );
+        assert_eq!(hash_message(signed.msg.as_bytes()), signed.h);
+        assert!(params.verify(&y, signed.msg.as_bytes(), &signed.sig));
+        let (k, x) = params
+            .brute_force_k(&y, &signed.h, &signed.sig, 0..1 << 16)
+            .unwrap();
+        assert_eq!(k, BigInt::from_u64(16575));
+        assert_eq!(fingerprint(&x), "0954edd5e0afe5542a4adf012611a91912a3ec16");
+    }
+
+    #[test]
+    fn test_dsa_repeated_k() {
+        let params = DsaParams::cryptopals();
+        let y = BigInt::from_hex(&Hex(
+            "2d026f4bf30195ede3a088da85e398ef869611d0f68f0713d51c9c1a3a26c95105d915e2d8cdf26d056b86b8a7b85519b1c23cc3ecdc6062650462e3063bd179c2a6581519f674a61f1d89a1fff27171ebc1b93d4dc57bceb7ae2430f98a6a4d83d8279ee65d71c1203d2c96d65ebbf7cce9d32971c3de5084cce04a2e147821".into(),
+        ))
+        .unwrap();
+        let messages = load_signed_messages("resources/chal44").unwrap();
+        assert_eq!(messages.len(), 10);
+        messages.iter().for_each(|m| {
+            assert_eq!(hash_message(m.msg.as_bytes()), m.h);
+            assert!(params.verify_hash(&y, &m.h, &m.sig));
+        });
+        let (_, x) = params.find_repeated_k(&y, &messages).unwrap();
+        assert_eq!(fingerprint(&x), "ca8f6f7c66fa362d40760d135b763eb8527d3d52");
+        assert_eq!(params.find_repeated_k(&y, &messages[..2]), None);
+    }
+
+    #[test]
+    fn test_dsa_g_zero() {
+        let honest = DsaParams::cryptopals();
+        let keys = honest.generate_keypair();
+        let tampered = DsaParams {
+            g: BigInt::zero(),
+            ..honest
+        };
+        let sig = tampered.magic_signature(&keys.y).unwrap();
+        assert_eq!(sig.r, BigInt::zero());
+        let no_range = DsaChecks {
+            signature_range: false,
+            generator: true,
+        };
+        let no_generator = DsaChecks {
+            signature_range: true,
+            generator: false,
+        };
+        for message in &[&b"Hello, world"[..], b"Goodbye, world"] {
+            assert!(tampered.verify_with(&keys.y, message, &sig, DsaChecks::none()));
+            assert!(!tampered.verify_with(&keys.y, message, &sig, no_range));
+            assert!(!tampered.verify_with(&keys.y, message, &sig, no_generator));
+            assert!(!tampered.verify(&keys.y, message, &sig));
+        }
+    }
+
+    #[test]
+    fn test_dsa_g_p_plus_one() {
+        let honest = DsaParams::cryptopals();
+        let keys = honest.generate_keypair();
+        let tampered = DsaParams {
+            g: &honest.p + &BigInt::one(),
+            ..honest.clone()
+        };
+        assert!(honest.has_valid_generator());
+        assert!(!tampered.has_valid_generator());
+        assert_eq!(honest.magic_signature(&keys.y), None);
+        let sig = tampered.magic_signature(&keys.y).unwrap();
+        let no_generator = DsaChecks {
+            signature_range: true,
+            generator: false,
+        };
+        for message in &[&b"Hello, world"[..], b"Goodbye, world"] {
+            assert!(tampered.verify_with(&keys.y, message, &sig, DsaChecks::none()));
+            assert!(tampered.verify_with(&keys.y, message, &sig, no_generator));
+            assert!(!tampered.verify(&keys.y, message, &sig));
+            assert!(!honest.verify(&keys.y, message, &sig));
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+pub fn md_padding(len: u64, endian: Endian) -> Vec<u8> {
+    let mut pad = vec![0x80];
+    while (len as usize + pad.len()) % 64 != 56 {
+        pad.push(0);
+    }
+    let bits = len.wrapping_mul(8);
+    match endian {
+        Endian::Big => pad.extend_from_slice(&bits.to_be_bytes()),
+        Endian::Little => pad.extend_from_slice(&bits.to_le_bytes()),
+    }
+    pad
+}
+
+pub fn buffered_update<F: FnMut(&[u8])>(buffer: &mut Vec<u8>, data: &[u8], mut compress: F) {
+    let mut data = data;
+    if !buffer.is_empty() {
+        let take = (64 - buffer.len()).min(data.len());
+        buffer.extend_from_slice(&data[..take]);
+        data = &data[take..];
+        if buffer.len() < 64 {
+            return;
+        }
+        compress(buffer);
+        buffer.clear();
+    }
+    let mut chunks = data.chunks_exact(64);
+    for block in &mut chunks {
+        compress(block);
+    }
+    buffer.extend_from_slice(chunks.remainder());
+}
+
+pub trait Hash: Clone {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
+    const ENDIAN: Endian;
+
+    fn new() -> Self;
+    fn from_digest(digest: &[u8], len: u64) -> Option<Self>;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn padding(len: u64) -> Vec<u8> {
+        md_padding(len, Self::ENDIAN)
+    }
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut h = Self::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::{md_padding, Endian};
+
+    #[test]
+    fn test_md_padding() {
+        (0..200).for_each(|len| {
+            assert_eq!((len + md_padding(len, Endian::Big).len() as u64) % 64, 0);
+        });
+        let pad = md_padding(3, Endian::Big);
+        assert_eq!(pad.len(), 61);
+        assert_eq!(pad[0], 0x80);
+        assert_eq!(pad[53..], [0, 0, 0, 0, 0, 0, 0, 24]);
+        let pad = md_padding(3, Endian::Little);
+        assert_eq!(pad[53..], [24, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(md_padding(56, Endian::Big).len(), 72);
+    }
+}
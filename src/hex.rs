@@ -17,6 +17,15 @@ pub const HEX: [char; 16] = [
 pub struct Hex(pub String);
 
 impl Hex {
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn parse(s: &str) -> Option<Hex> {
+        if s.len() % 2 == 0 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Some(Hex(s.to_ascii_lowercase()))
+        } else {
+            None
+        }
+    }
+
     pub fn to_bytes(&self) -> Bytes {
         Bytes(
             self.0
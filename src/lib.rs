@@ -1,7 +1,21 @@
-mod base64;
-mod bytes;
+pub mod base64;
+pub mod bignum;
+pub mod bytes;
+pub mod channel;
+pub mod dh;
+pub mod dsa;
 mod eng;
-mod hex;
+pub mod hash;
+pub mod hex;
+pub mod mac;
+pub mod md4;
+pub mod mt19937;
+pub mod oracles;
+pub mod rsa;
+pub mod server;
+pub mod sha1;
+pub mod sha256;
+pub mod srp;
 
 #[cfg(test)]
 mod tests {
@@ -12,6 +26,8 @@ mod tests {
     use crate::bytes::Bytes;
     use crate::eng::{char_freq_score, log_weight_score};
     use crate::hex::Hex;
+    use crate::mt19937::Mt19937;
+    use crate::oracles::{recover_ctr_plaintext, CtrEditOracle};
     use itertools::Itertools;
     use openssl::aes::AesKey;
     use openssl::symm::{decrypt, Cipher};
@@ -199,4 +215,24 @@ mod tests {
             .into_string()
         );
     }
+
+    #[test]
+    fn challenge25() {
+        let content = B64(BufReader::new(File::open("resources/chal7").unwrap())
+            .lines()
+            .map(|l| l.unwrap())
+            .collect::<String>());
+        let inp = content.to_bytes();
+        let plaintext = decrypt(
+            Cipher::aes_128_ecb(),
+            b"YELLOW SUBMARINE",
+            None,
+            inp.0.as_slice(),
+        )
+        .unwrap();
+        let oracle = CtrEditOracle::with_rng(&mut Mt19937::seed(25));
+        let ciphertext = oracle.encrypt(Bytes(plaintext.clone()));
+        assert_ne!(ciphertext.0, plaintext);
+        assert_eq!(recover_ctr_plaintext(&oracle, &ciphertext).0, plaintext);
+    }
 }
@@ -0,0 +1,289 @@
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use crate::bytes::Bytes;
+use crate::hash::Hash;
+
+pub struct SecretPrefixMac<H: Hash> {
+    key: Bytes,
+    hash: PhantomData<H>,
+}
+
+impl<H: Hash> SecretPrefixMac<H> {
+    pub fn new(key: Bytes) -> Self {
+        SecretPrefixMac {
+            key,
+            hash: PhantomData,
+        }
+    }
+
+    pub fn mac(&self, message: &[u8]) -> Vec<u8> {
+        let mut h = H::new();
+        h.update(&self.key.0);
+        h.update(message);
+        h.finalize()
+    }
+
+    pub fn verify(&self, message: &[u8], mac: &[u8]) -> bool {
+        self.mac(message) == mac
+    }
+}
+
+pub fn length_extension<H: Hash>(
+    message: &[u8],
+    mac: &[u8],
+    suffix: &[u8],
+    key_len: usize,
+) -> Option<(Vec<u8>, Vec<u8>)> {
+    let glue = H::padding((key_len + message.len()) as u64);
+    let mut h = H::from_digest(mac, (key_len + message.len() + glue.len()) as u64)?;
+    h.update(suffix);
+    Some(([message, &glue, suffix].concat(), h.finalize()))
+}
+
+pub fn length_extension_attack<H: Hash>(
+    oracle: &SecretPrefixMac<H>,
+    message: &[u8],
+    mac: &[u8],
+    suffix: &[u8],
+    key_lens: RangeInclusive<usize>,
+) -> Option<(usize, Vec<u8>, Vec<u8>)> {
+    if mac.len() != H::OUTPUT_SIZE {
+        return None;
+    }
+    key_lens
+        .filter_map(|key_len| {
+            let (forged, forged_mac) = length_extension::<H>(message, mac, suffix, key_len)?;
+            Some((key_len, forged, forged_mac))
+        })
+        .find(|(_, forged, forged_mac)| oracle.verify(forged, forged_mac))
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b.iter()) {
+        if x != y {
+            return false;
+        }
+        let start = Instant::now();
+        while start.elapsed() < delay {}
+    }
+    true
+}
+
+#[derive(Clone)]
+pub struct Hmac<H: Hash> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: Hash> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        let mut block_key = if key.len() > H::BLOCK_SIZE {
+            H::digest(key)
+        } else {
+            key.to_vec()
+        };
+        block_key.resize(H::BLOCK_SIZE, 0);
+        let mut inner = H::new();
+        inner.update(&block_key.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+        let mut outer = H::new();
+        outer.update(&block_key.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+        Hmac { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+
+    pub fn mac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut h = Hmac::<H>::new(key);
+        h.update(data);
+        h.finalize()
+    }
+
+    pub fn verify(self, tag: &[u8]) -> bool {
+        constant_time_eq(&self.finalize(), tag)
+    }
+
+    pub fn verify_insecure(self, tag: &[u8], delay: Duration) -> bool {
+        insecure_compare(&self.finalize(), tag, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::sign::Signer;
+
+    use crate::bytes::Bytes;
+    use crate::hash::Hash;
+    use crate::hex::Hex;
+    use crate::mac::{length_extension, length_extension_attack, Hmac, SecretPrefixMac};
+    use crate::md4::Md4;
+    use crate::mt19937::Mt19937;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+
+    const MESSAGE: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    fn forge<H: Hash>(seed: u32) {
+        let mut rng = Mt19937::seed(seed);
+        let key_len = 1 + (rng.next_u32() % 64) as usize;
+        let oracle = SecretPrefixMac::<H>::new(Bytes::random_with(&mut rng, key_len));
+        let mac = oracle.mac(MESSAGE);
+        let (guessed_len, forged, forged_mac) =
+            length_extension_attack(&oracle, MESSAGE, &mac, b";admin=true", 0..=64).unwrap();
+        assert_eq!(guessed_len, key_len);
+        assert!(forged.starts_with(MESSAGE));
+        assert!(forged.ends_with(b";admin=true"));
+        assert!(oracle.verify(&forged, &forged_mac));
+        assert_eq!(
+            length_extension_attack(&oracle, MESSAGE, &mac[1..], b";admin=true", 0..=64),
+            None
+        );
+        assert_eq!(
+            length_extension::<H>(MESSAGE, &[], b";admin=true", key_len),
+            None
+        );
+    }
+
+    #[test]
+    fn test_secret_prefix_mac() {
+        let oracle = SecretPrefixMac::<Sha1>::new(Bytes::from_string("YELLOW SUBMARINE".into()));
+        let mac = oracle.mac(b"message");
+        assert_eq!(mac, Sha1::digest(b"YELLOW SUBMARINEmessage"));
+        assert!(oracle.verify(b"message", &mac));
+        assert!(!oracle.verify(b"massage", &mac));
+    }
+
+    #[test]
+    fn test_sha1_length_extension_attack() {
+        forge::<Sha1>(29);
+    }
+
+    #[test]
+    fn test_md4_length_extension_attack() {
+        forge::<Md4>(30);
+    }
+
+    fn hmac_hex<H: Hash>(key: &[u8], data: &[u8]) -> String {
+        Bytes(Hmac::<H>::mac(key, data)).to_hex().0
+    }
+
+    #[test]
+    fn test_hmac_sha1_rfc2202() {
+        let key: Vec<u8> = (1..=25).collect();
+        assert_eq!(
+            hmac_hex::<Sha1>(&[0x0b; 20], b"Hi There"),
+            "b617318655057264e28bc0b6fb378c8ef146be00"
+        );
+        assert_eq!(
+            hmac_hex::<Sha1>(b"Jefe", b"what do ya want for nothing?"),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+        assert_eq!(
+            hmac_hex::<Sha1>(&[0xaa; 20], &[0xdd; 50]),
+            "125d7342b9ac11cd91a39af48aa17b4f63f175d3"
+        );
+        assert_eq!(
+            hmac_hex::<Sha1>(&key, &[0xcd; 50]),
+            "4c9007f4026250c6bc8414f9bf50c86c2d7235da"
+        );
+        assert_eq!(
+            hmac_hex::<Sha1>(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+        );
+        assert_eq!(
+            hmac_hex::<Sha1>(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data"
+            ),
+            "e8e99d0f45237d786d6bbaa7965c7808bbff1a91"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        let key: Vec<u8> = (1..=25).collect();
+        assert_eq!(
+            hmac_hex::<Sha256>(&[0x0b; 20], b"Hi There"),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hmac_hex::<Sha256>(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac_hex::<Sha256>(&[0xaa; 20], &[0xdd; 50]),
+            "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe"
+        );
+        assert_eq!(
+            hmac_hex::<Sha256>(&key, &[0xcd; 50]),
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b"
+        );
+        assert_eq!(
+            hmac_hex::<Sha256>(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        assert_eq!(
+            hmac_hex::<Sha256>(&[0xaa; 131], b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm."),
+            "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2"
+        );
+    }
+
+    #[test]
+    fn test_hmac_matches_openssl() {
+        (1..100).for_each(|len| {
+            let key = Bytes::random(len);
+            let data = Bytes::random(len * 3);
+            let pkey = PKey::hmac(&key.0).unwrap();
+            let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+            signer.update(&data.0).unwrap();
+            assert_eq!(
+                Hmac::<Sha256>::mac(&key.0, &data.0),
+                signer.sign_to_vec().unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn test_hmac_verify() {
+        let tag = Hex("b617318655057264e28bc0b6fb378c8ef146be00".into()).to_bytes();
+        let mut h = Hmac::<Sha1>::new(&[0x0b; 20]);
+        h.update(b"Hi There");
+        assert!(h.clone().verify(&tag.0));
+        assert!(h.clone().verify_insecure(&tag.0, Duration::from_micros(1)));
+        assert!(!h.clone().verify(&tag.0[..19]));
+        let mut bad = tag.0.clone();
+        bad[19] ^= 1;
+        assert!(!h.clone().verify(&bad));
+        assert!(!h.verify_insecure(&bad, Duration::from_micros(1)));
+    }
+}
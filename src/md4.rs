@@ -0,0 +1,233 @@
+use crate::hash::{buffered_update, md_padding, Endian, Hash};
+
+const H0: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+
+fn compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut x = [0u32; 16];
+    x.iter_mut()
+        .zip(block.chunks_exact(4))
+        .for_each(|(x, b)| *x = u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let [mut a, mut b, mut c, mut d] = *state;
+
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    for &i in &[0, 4, 8, 12] {
+        a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+        d = d
+            .wrapping_add(f(a, b, c))
+            .wrapping_add(x[i + 1])
+            .rotate_left(7);
+        c = c
+            .wrapping_add(f(d, a, b))
+            .wrapping_add(x[i + 2])
+            .rotate_left(11);
+        b = b
+            .wrapping_add(f(c, d, a))
+            .wrapping_add(x[i + 3])
+            .rotate_left(19);
+    }
+    for &i in &[0, 1, 2, 3] {
+        a = a
+            .wrapping_add(g(b, c, d))
+            .wrapping_add(x[i])
+            .wrapping_add(0x5a82_7999)
+            .rotate_left(3);
+        d = d
+            .wrapping_add(g(a, b, c))
+            .wrapping_add(x[i + 4])
+            .wrapping_add(0x5a82_7999)
+            .rotate_left(5);
+        c = c
+            .wrapping_add(g(d, a, b))
+            .wrapping_add(x[i + 8])
+            .wrapping_add(0x5a82_7999)
+            .rotate_left(9);
+        b = b
+            .wrapping_add(g(c, d, a))
+            .wrapping_add(x[i + 12])
+            .wrapping_add(0x5a82_7999)
+            .rotate_left(13);
+    }
+    for &i in &[0, 2, 1, 3] {
+        a = a
+            .wrapping_add(h(b, c, d))
+            .wrapping_add(x[i])
+            .wrapping_add(0x6ed9_eba1)
+            .rotate_left(3);
+        d = d
+            .wrapping_add(h(a, b, c))
+            .wrapping_add(x[i + 8])
+            .wrapping_add(0x6ed9_eba1)
+            .rotate_left(9);
+        c = c
+            .wrapping_add(h(d, a, b))
+            .wrapping_add(x[i + 4])
+            .wrapping_add(0x6ed9_eba1)
+            .rotate_left(11);
+        b = b
+            .wrapping_add(h(c, d, a))
+            .wrapping_add(x[i + 12])
+            .wrapping_add(0x6ed9_eba1)
+            .rotate_left(15);
+    }
+
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
+
+#[derive(Clone)]
+pub struct Md4 {
+    state: [u32; 4],
+    buffer: Vec<u8>,
+    len: u64,
+}
+
+impl Md4 {
+    pub fn new() -> Self {
+        Md4::from_state(H0, 0)
+    }
+
+    pub fn from_state(state: [u32; 4], len: u64) -> Self {
+        Md4 {
+            state,
+            buffer: Vec::with_capacity(64),
+            len,
+        }
+    }
+
+    pub fn from_digest(digest: &[u8; 16], len: u64) -> Self {
+        let mut state = [0; 4];
+        state
+            .iter_mut()
+            .zip(digest.chunks_exact(4))
+            .for_each(|(s, b)| *s = u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        Md4::from_state(state, len)
+    }
+
+    pub fn state(&self) -> [u32; 4] {
+        self.state
+    }
+
+    pub fn processed_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+        let state = &mut self.state;
+        buffered_update(&mut self.buffer, data, |block| compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        let pad = md_padding(self.len, Endian::Little);
+        self.update(&pad);
+        let mut res = [0; 16];
+        res.chunks_exact_mut(4)
+            .zip(self.state.iter())
+            .for_each(|(r, s)| r.copy_from_slice(&s.to_le_bytes()));
+        res
+    }
+
+    pub fn digest(data: &[u8]) -> [u8; 16] {
+        let mut h = Md4::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+impl Default for Md4 {
+    fn default() -> Self {
+        Md4::new()
+    }
+}
+
+impl Hash for Md4 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 16;
+    const ENDIAN: Endian = Endian::Little;
+
+    fn new() -> Self {
+        Md4::new()
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Option<Self> {
+        if digest.len() != Self::OUTPUT_SIZE {
+            return None;
+        }
+        let mut d = [0; 16];
+        d.copy_from_slice(digest);
+        Some(Md4::from_digest(&d, len))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Md4::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Md4::finalize(self).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::hash::{md_padding, Endian};
+    use crate::md4::Md4;
+
+    fn hex_digest(data: &[u8]) -> String {
+        Bytes(Md4::digest(data).to_vec()).to_hex().0
+    }
+
+    #[test]
+    fn test_md4_vectors() {
+        assert_eq!(hex_digest(b""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex_digest(b"a"), "bde52cb31de33e46245e05fbdbd6fb24");
+        assert_eq!(hex_digest(b"abc"), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(
+            hex_digest(b"message digest"),
+            "d9130a8164549fe818874806e1c7014b"
+        );
+        assert_eq!(
+            hex_digest(b"abcdefghijklmnopqrstuvwxyz"),
+            "d79e1c308aa5bbcdeea8ed63df412da9"
+        );
+        assert_eq!(
+            hex_digest(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+            "043f8582f241db351ce627e153e7f0e4"
+        );
+        assert_eq!(
+            hex_digest(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "e33b4ddc9c38f2199c3e7b164fcc0536"
+        );
+    }
+
+    #[test]
+    fn test_md4_incremental() {
+        let data = Bytes::random(300);
+        (0..300).for_each(|len| {
+            let mut h = Md4::new();
+            data.0[..len].chunks(7).for_each(|c| h.update(c));
+            assert_eq!(h.finalize(), Md4::digest(&data.0[..len]));
+        });
+    }
+
+    #[test]
+    fn test_md4_from_state() {
+        let prefix = b"YELLOW SUBMARINE";
+        let glued = [
+            &prefix[..],
+            &md_padding(prefix.len() as u64, Endian::Little),
+        ]
+        .concat();
+        let mut h = Md4::from_digest(&Md4::digest(prefix), glued.len() as u64);
+        assert_eq!(h.processed_len(), 64);
+        h.update(b"suffix");
+        assert_eq!(h.finalize(), Md4::digest(&[&glued[..], b"suffix"].concat()));
+    }
+}
@@ -0,0 +1,531 @@
+use std::cell::Cell;
+use std::ops::RangeInclusive;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand_core::{impls, Error, RngCore, SeedableRng};
+use rayon::prelude::*;
+
+use crate::bytes::Bytes;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+const NN: usize = 312;
+const MM: usize = 156;
+const MATRIX_A_64: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK_64: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK_64: u64 = 0x7fff_ffff;
+
+pub const DEFAULT_SEED: u32 = 5489;
+
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^= y >> 18;
+    y
+}
+
+fn undo_right_shift_xor(y: u32, shift: u32) -> u32 {
+    let mut res = y;
+    for _ in 0..32 / shift {
+        res = y ^ (res >> shift);
+    }
+    res
+}
+
+fn undo_left_shift_xor(y: u32, shift: u32, mask: u32) -> u32 {
+    let mut res = y;
+    for _ in 0..32 / shift {
+        res = y ^ ((res << shift) & mask);
+    }
+    res
+}
+
+pub fn untemper(y: u32) -> u32 {
+    let y = undo_right_shift_xor(y, 18);
+    let y = undo_left_shift_xor(y, 15, 0xefc6_0000);
+    let y = undo_left_shift_xor(y, 7, 0x9d2c_5680);
+    undo_right_shift_xor(y, 11)
+}
+
+pub fn clone_mt19937(outputs: &[u32; N]) -> Mt19937 {
+    let mut mt = [0; N];
+    mt.iter_mut()
+        .zip(outputs.iter())
+        .for_each(|(m, o)| *m = untemper(*o));
+    Mt19937::from_state(mt)
+}
+
+#[derive(Clone)]
+pub struct Mt19937 {
+    mt: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn seed(seed: u32) -> Self {
+        let mut mt = [0; N];
+        mt[0] = seed;
+        for i in 1..N {
+            mt[i] = 1_812_433_253u32
+                .wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 { mt, index: N }
+    }
+
+    pub fn from_state(mt: [u32; N]) -> Self {
+        Mt19937 { mt, index: N }
+    }
+
+    pub fn seed_by_array(key: &[u32]) -> Self {
+        assert!(!key.is_empty(), "seed_by_array needs a non-empty key");
+        let mut res = Mt19937::seed(19_650_218);
+        let mt = &mut res.mt;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N.max(key.len()) {
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 30)).wrapping_mul(1_664_525))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 30)).wrapping_mul(1_566_083_941))
+                .wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        mt[0] = UPPER_MASK;
+        res
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.mt[i] & UPPER_MASK) | (self.mt[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 0 { 0 } else { MATRIX_A };
+            self.mt[i] = self.mt[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.mt[self.index];
+        self.index += 1;
+        temper(y)
+    }
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937 {
+    type Seed = [u8; 4];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Mt19937::seed(u32::from_le_bytes(seed))
+    }
+}
+
+impl Default for Mt19937 {
+    fn default() -> Self {
+        Mt19937::seed(DEFAULT_SEED)
+    }
+}
+
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    mt: [u64; NN],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    pub fn seed(seed: u64) -> Self {
+        let mut mt = [0; NN];
+        mt[0] = seed;
+        for i in 1..NN {
+            mt[i] = 6_364_136_223_846_793_005u64
+                .wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 62))
+                .wrapping_add(i as u64);
+        }
+        Mt19937_64 { mt, index: NN }
+    }
+
+    pub fn seed_by_array(key: &[u64]) -> Self {
+        assert!(!key.is_empty(), "seed_by_array needs a non-empty key");
+        let mut res = Mt19937_64::seed(19_650_218);
+        let mt = &mut res.mt;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..NN.max(key.len()) {
+            mt[i] = (mt[i]
+                ^ (mt[i - 1] ^ (mt[i - 1] >> 62)).wrapping_mul(3_935_559_000_370_003_845))
+            .wrapping_add(key[j])
+            .wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= NN {
+                mt[0] = mt[NN - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..NN - 1 {
+            mt[i] = (mt[i]
+                ^ (mt[i - 1] ^ (mt[i - 1] >> 62)).wrapping_mul(2_862_933_555_777_941_757))
+            .wrapping_sub(i as u64);
+            i += 1;
+            if i >= NN {
+                mt[0] = mt[NN - 1];
+                i = 1;
+            }
+        }
+        mt[0] = 1 << 63;
+        res
+    }
+
+    fn twist(&mut self) {
+        for i in 0..NN {
+            let x = (self.mt[i] & UPPER_MASK_64) | (self.mt[(i + 1) % NN] & LOWER_MASK_64);
+            let mag = if x & 1 == 0 { 0 } else { MATRIX_A_64 };
+            self.mt[i] = self.mt[(i + MM) % NN] ^ (x >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= NN {
+            self.twist();
+        }
+        let mut x = self.mt[self.index];
+        self.index += 1;
+        x ^= (x >> 29) & 0x5555_5555_5555_5555;
+        x ^= (x << 17) & 0x71d6_7fff_eda6_0000;
+        x ^= (x << 37) & 0xfff7_eee0_0000_0000;
+        x ^= x >> 43;
+        x
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    fn next_u32(&mut self) -> u32 {
+        (Mt19937_64::next_u64(self) >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Mt19937_64::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937_64 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Mt19937_64::seed(u64::from_le_bytes(seed))
+    }
+}
+
+impl Default for Mt19937_64 {
+    fn default() -> Self {
+        Mt19937_64::seed(DEFAULT_SEED as u64)
+    }
+}
+
+pub trait Clock {
+    fn now(&self) -> u32;
+    fn sleep(&self, secs: u32);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u32 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32
+    }
+
+    fn sleep(&self, secs: u32) {
+        thread::sleep(Duration::from_secs(secs as u64));
+    }
+}
+
+pub struct MockClock(Cell<u32>);
+
+impl MockClock {
+    pub fn new(now: u32) -> Self {
+        MockClock(Cell::new(now))
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u32 {
+        self.0.get()
+    }
+
+    fn sleep(&self, secs: u32) {
+        self.0.set(self.0.get().saturating_add(secs));
+    }
+}
+
+pub fn time_seeded_output<C: Clock, R: RngCore>(clock: &C, rng: &mut R) -> u32 {
+    clock.sleep(40 + rng.next_u32() % 961);
+    let mut mt = Mt19937::seed(clock.now());
+    clock.sleep(40 + rng.next_u32() % 961);
+    mt.next_u32()
+}
+
+pub fn recover_time_seed(first_output: u32, window: RangeInclusive<u32>) -> Option<u32> {
+    window
+        .into_par_iter()
+        .find_any(|seed| Mt19937::seed(*seed).next_u32() == first_output)
+}
+
+pub fn password_reset_token<C: Clock>(clock: &C, len: usize) -> Vec<u8> {
+    let mut token = vec![0; len];
+    Mt19937::seed(clock.now()).fill_bytes(&mut token);
+    token
+}
+
+pub fn find_token_seed<C: Clock>(token: &[u8], clock: &C, window_secs: u32) -> Option<u32> {
+    if token.is_empty() {
+        return None;
+    }
+    let now = clock.now();
+    (now.saturating_sub(window_secs)..=now)
+        .into_par_iter()
+        .find_any(|seed| {
+            let mut candidate = vec![0; token.len()];
+            Mt19937::seed(*seed).fill_bytes(&mut candidate);
+            candidate == token
+        })
+}
+
+pub fn mt_keystream(seed: u16, len: usize) -> Bytes {
+    let mut keystream = vec![0; len];
+    Mt19937::seed(seed as u32).fill_bytes(&mut keystream);
+    Bytes(keystream)
+}
+
+pub fn mt_stream_cipher(data: &Bytes, seed: u16) -> Bytes {
+    data.xor(&mt_keystream(seed, data.0.len()))
+}
+
+pub fn mt_prefix_oracle<R: RngCore>(rng: &mut R, seed: u16, plaintext: &[u8]) -> Bytes {
+    let mut prefix = vec![0; 5 + (rng.next_u32() % 16) as usize];
+    rng.fill_bytes(&mut prefix);
+    prefix.extend_from_slice(plaintext);
+    mt_stream_cipher(&Bytes(prefix), seed)
+}
+
+pub fn recover_mt_stream_seed(ciphertext: &Bytes, known_suffix: &[u8]) -> Option<u16> {
+    if known_suffix.is_empty() || known_suffix.len() > ciphertext.0.len() {
+        return None;
+    }
+    let offset = ciphertext.0.len() - known_suffix.len();
+    (0..=u16::MAX).into_par_iter().find_any(|seed| {
+        let keystream = mt_keystream(*seed, ciphertext.0.len());
+        ciphertext.0[offset..]
+            .iter()
+            .zip(keystream.0[offset..].iter())
+            .map(|(c, k)| c ^ k)
+            .eq(known_suffix.iter().copied())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::{RngCore, SeedableRng};
+
+    use crate::bytes::Bytes;
+    use crate::mt19937::{
+        clone_mt19937, find_token_seed, mt_prefix_oracle, mt_stream_cipher, password_reset_token,
+        recover_mt_stream_seed, recover_time_seed, temper, time_seeded_output, untemper, Clock,
+        MockClock, Mt19937, Mt19937_64,
+    };
+
+    #[test]
+    fn test_mt19937_default_seed() {
+        let mut rng = Mt19937::default();
+        assert_eq!(rng.next_u32(), 3_499_211_612);
+        let mut rng = Mt19937::default();
+        let last = (0..10_000).map(|_| rng.next_u32()).last().unwrap();
+        assert_eq!(last, 4_123_659_995);
+    }
+
+    #[test]
+    fn test_mt19937_seed_by_array() {
+        let mut rng = Mt19937::seed_by_array(&[0x123, 0x234, 0x345, 0x456]);
+        let out = (0..10).map(|_| rng.next_u32()).collect::<Vec<u32>>();
+        assert_eq!(
+            out,
+            vec![
+                1_067_595_299,
+                955_945_823,
+                477_289_528,
+                4_107_218_783,
+                4_228_976_476,
+                3_344_332_714,
+                3_355_579_695,
+                227_628_506,
+                810_200_273,
+                2_591_290_167
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mt19937_64_default_seed() {
+        let mut rng = Mt19937_64::default();
+        let last = (0..10_000).map(|_| rng.next_u64()).last().unwrap();
+        assert_eq!(last, 9_981_545_732_273_789_042);
+    }
+
+    #[test]
+    #[should_panic(expected = "non-empty key")]
+    fn test_seed_by_empty_array() {
+        Mt19937::seed_by_array(&[]);
+    }
+
+    #[test]
+    fn test_mt19937_64_seed_by_array() {
+        let mut rng = Mt19937_64::seed_by_array(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let out = (0..5).map(|_| rng.next_u64()).collect::<Vec<u64>>();
+        assert_eq!(
+            out,
+            vec![
+                7_266_447_313_870_364_031,
+                4_946_485_549_665_804_864,
+                16_945_909_448_695_747_420,
+                16_394_063_075_524_226_720,
+                4_873_882_236_456_199_058
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut a = Mt19937::from_seed(1234u32.to_le_bytes());
+        let mut b = Mt19937::seed(1234);
+        let mut bytes = [0; 8];
+        a.fill_bytes(&mut bytes);
+        let expected = [b.next_u32().to_le_bytes(), b.next_u32().to_le_bytes()].concat();
+        assert_eq!(bytes.to_vec(), expected);
+    }
+
+    #[test]
+    fn test_untemper() {
+        let mut rng = Mt19937::seed(42);
+        (0..100_000).for_each(|_| {
+            let y = rng.next_u32();
+            assert_eq!(untemper(temper(y)), y);
+            assert_eq!(temper(untemper(y)), y);
+        });
+        [0, 1, u32::MAX, 0x8000_0000].iter().for_each(|y| {
+            assert_eq!(untemper(temper(*y)), *y);
+        });
+    }
+
+    #[test]
+    fn test_clone_mt19937() {
+        let mut rng = Mt19937::seed(0xdead_beef);
+        (0..1000).for_each(|_| {
+            rng.next_u32();
+        });
+        let mut outputs = [0; 624];
+        outputs.iter_mut().for_each(|o| *o = rng.next_u32());
+        let mut cloned = clone_mt19937(&outputs);
+        (0..5000).for_each(|_| assert_eq!(cloned.next_u32(), rng.next_u32()));
+    }
+
+    #[test]
+    fn test_recover_time_seed() {
+        let clock = MockClock::new(1_600_000_000);
+        let mut rng = Mt19937::seed(7);
+        let output = time_seeded_output(&clock, &mut rng);
+        let now = clock.now();
+        let seed = recover_time_seed(output, now - 2000..=now).unwrap();
+        assert_eq!(Mt19937::seed(seed).next_u32(), output);
+        assert!(seed > 1_600_000_000 && seed < now);
+    }
+
+    #[test]
+    fn test_find_token_seed() {
+        let clock = MockClock::new(1_600_000_000);
+        let token = password_reset_token(&clock, 16);
+        clock.sleep(300);
+        assert_eq!(find_token_seed(&token, &clock, 3600), Some(1_600_000_000));
+        let mut random_token = vec![0; 16];
+        Mt19937::seed(12345).fill_bytes(&mut random_token);
+        assert_eq!(find_token_seed(&random_token, &clock, 3600), None);
+        assert_eq!(find_token_seed(&[], &clock, 3600), None);
+    }
+
+    #[test]
+    fn test_mt_stream_cipher() {
+        let plaintext = Bytes::from_string("Some Crypto Text".into());
+        let ciphertext = mt_stream_cipher(&plaintext, 0xbeef);
+        assert_ne!(ciphertext.0, plaintext.0);
+        assert_eq!(
+            mt_stream_cipher(&ciphertext, 0xbeef).into_string(),
+            "Some Crypto Text"
+        );
+    }
+
+    #[test]
+    fn test_recover_mt_stream_seed() {
+        let mut rng = Mt19937::seed(2021);
+        let known = [b'A'; 14];
+        let ciphertext = mt_prefix_oracle(&mut rng, 0x1d2c, &known);
+        let seed = recover_mt_stream_seed(&ciphertext, &known).unwrap();
+        assert_eq!(seed, 0x1d2c);
+        let plaintext = mt_stream_cipher(&ciphertext, seed);
+        assert!(plaintext.0.ends_with(&known));
+        assert_eq!(recover_mt_stream_seed(&ciphertext, &[]), None);
+    }
+}
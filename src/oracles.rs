@@ -0,0 +1,641 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use openssl::rand::rand_bytes;
+use rand_core::RngCore;
+
+use crate::bignum::BigInt;
+use crate::bytes::{Bytes, EditOutOfRange};
+use crate::rsa::{i2osp, pkcs1_unpad, RsaKeyPair, RsaPublicKey, E65537};
+use crate::sha256::Sha256;
+
+pub struct CtrEditOracle {
+    key: Bytes,
+    nonce: u64,
+}
+
+impl CtrEditOracle {
+    pub fn new() -> Self {
+        let mut nonce = [0; 8];
+        rand_bytes(&mut nonce).unwrap();
+        CtrEditOracle {
+            key: Bytes::random(16),
+            nonce: u64::from_le_bytes(nonce),
+        }
+    }
+
+    pub fn with_rng<R: RngCore>(rng: &mut R) -> Self {
+        CtrEditOracle {
+            key: Bytes::random_with(rng, 16),
+            nonce: rng.next_u64(),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: Bytes) -> Bytes {
+        plaintext.encrypt_CTR(&self.key.0, self.nonce)
+    }
+
+    pub fn edit(
+        &self,
+        ciphertext: &mut Bytes,
+        offset: usize,
+        newtext: &[u8],
+    ) -> Result<(), EditOutOfRange> {
+        ciphertext.edit_CTR(&self.key.0, self.nonce, offset, newtext)
+    }
+}
+
+impl Default for CtrEditOracle {
+    fn default() -> Self {
+        CtrEditOracle::new()
+    }
+}
+
+pub const COOKIE_PREFIX: &str = "comment1=cooking%20MCs;userdata=";
+pub const COOKIE_SUFFIX: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockMode {
+    Cbc,
+    Ctr,
+}
+
+pub struct CookieOracle {
+    key: Bytes,
+    iv: Bytes,
+    nonce: u64,
+    mode: BlockMode,
+}
+
+impl CookieOracle {
+    pub fn new(mode: BlockMode) -> Self {
+        let mut nonce = [0; 8];
+        rand_bytes(&mut nonce).unwrap();
+        CookieOracle {
+            key: Bytes::random(16),
+            iv: Bytes::random(16),
+            nonce: u64::from_le_bytes(nonce),
+            mode,
+        }
+    }
+
+    pub fn with_rng<R: RngCore>(rng: &mut R, mode: BlockMode) -> Self {
+        CookieOracle {
+            key: Bytes::random_with(rng, 16),
+            iv: Bytes::random_with(rng, 16),
+            nonce: rng.next_u64(),
+            mode,
+        }
+    }
+
+    pub fn mode(&self) -> BlockMode {
+        self.mode
+    }
+
+    pub fn encrypt(&self, userdata: &str) -> Bytes {
+        let quoted = userdata.replace(';', "%3B").replace('=', "%3D");
+        let plaintext = Bytes::from_string(format!("{}{}{}", COOKIE_PREFIX, quoted, COOKIE_SUFFIX));
+        match self.mode {
+            BlockMode::Cbc => plaintext.encrypt_CBC(&self.key.0, &self.iv.0),
+            BlockMode::Ctr => plaintext.encrypt_CTR(&self.key.0, self.nonce),
+        }
+    }
+
+    pub fn is_admin(&self, ciphertext: &Bytes) -> bool {
+        let plaintext = match self.mode {
+            BlockMode::Cbc => ciphertext.clone().decrypt_CBC(&self.key.0, &self.iv.0),
+            BlockMode::Ctr => ciphertext.clone().decrypt_CTR(&self.key.0, self.nonce),
+        };
+        plaintext.into_string().contains(";admin=true;")
+    }
+}
+
+pub fn cookie_bitflip_attack(oracle: &CookieOracle, target: &[u8]) -> Bytes {
+    assert!(target.len() <= 16);
+    let filler = "A".repeat(16 + target.len());
+    let mut ciphertext = oracle.encrypt(filler.as_str());
+    let target_pos = COOKIE_PREFIX.len() + 16;
+    let flip_pos = match oracle.mode() {
+        BlockMode::Cbc => target_pos - 16,
+        BlockMode::Ctr => target_pos,
+    };
+    ciphertext.0[flip_pos..flip_pos + target.len()]
+        .iter_mut()
+        .zip(target.iter())
+        .for_each(|(c, t)| *c ^= b'A' ^ t);
+    ciphertext
+}
+
+#[derive(Debug)]
+pub struct InvalidAscii(pub Bytes);
+
+pub struct IvKeyOracle {
+    key: Bytes,
+}
+
+impl IvKeyOracle {
+    pub fn new() -> Self {
+        IvKeyOracle {
+            key: Bytes::random(16),
+        }
+    }
+
+    pub fn with_rng<R: RngCore>(rng: &mut R) -> Self {
+        IvKeyOracle {
+            key: Bytes::random_with(rng, 16),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: Bytes) -> Bytes {
+        plaintext.encrypt_CBC(&self.key.0, &self.key.0)
+    }
+
+    pub fn decrypt(&self, ciphertext: &Bytes) -> Result<(), InvalidAscii> {
+        let plaintext = ciphertext.clone().decrypt_CBC(&self.key.0, &self.key.0);
+        if plaintext.0.is_ascii() {
+            Ok(())
+        } else {
+            Err(InvalidAscii(plaintext))
+        }
+    }
+}
+
+impl Default for IvKeyOracle {
+    fn default() -> Self {
+        IvKeyOracle::new()
+    }
+}
+
+pub fn recover_iv_key(oracle: &IvKeyOracle, ciphertext: &Bytes) -> Option<Bytes> {
+    if ciphertext.0.len() < 48 {
+        return None;
+    }
+    let c1 = &ciphertext.0[..16];
+    let forged = Bytes([c1, &[0; 16], c1, &ciphertext.0[48..]].concat());
+    match oracle.decrypt(&forged) {
+        Ok(()) => None,
+        Err(InvalidAscii(plaintext)) => {
+            Some(Bytes(plaintext.0[..16].to_vec()).xor(&Bytes(plaintext.0[32..48].to_vec())))
+        }
+    }
+}
+
+pub fn recover_ctr_plaintext(oracle: &CtrEditOracle, ciphertext: &Bytes) -> Bytes {
+    let mut res = Bytes(ciphertext.0.clone());
+    oracle.edit(&mut res, 0, &ciphertext.0).unwrap();
+    res
+}
+
+pub trait RsaDecryptOracle {
+    fn public_key(&self) -> &RsaPublicKey;
+    fn decrypt(&self, ciphertext: &BigInt) -> Option<BigInt>;
+}
+
+pub struct UnpaddedRsaOracle {
+    pair: RsaKeyPair,
+    seen: Mutex<HashSet<[u8; 32]>>,
+}
+
+impl UnpaddedRsaOracle {
+    pub fn new(pair: RsaKeyPair) -> Self {
+        UnpaddedRsaOracle {
+            pair,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn generate(bits: usize) -> Self {
+        UnpaddedRsaOracle::new(RsaKeyPair::generate(bits, E65537))
+    }
+}
+
+impl RsaDecryptOracle for UnpaddedRsaOracle {
+    fn public_key(&self) -> &RsaPublicKey {
+        &self.pair.public
+    }
+
+    fn decrypt(&self, ciphertext: &BigInt) -> Option<BigInt> {
+        let encoded = i2osp(ciphertext, self.pair.public.size()).ok()?;
+        if !self.seen.lock().unwrap().insert(Sha256::digest(&encoded.0)) {
+            return None;
+        }
+        self.pair.private.decrypt(ciphertext).ok()
+    }
+}
+
+pub fn recover_unpadded_rsa<O: RsaDecryptOracle>(
+    oracle: &O,
+    ciphertext: &BigInt,
+) -> Option<BigInt> {
+    let key = oracle.public_key();
+    let (s, s_inv) = loop {
+        let two = BigInt::from_u64(2);
+        let s = &BigInt::random_below(&(&key.n - &two)) + &two;
+        if let Some(s_inv) = s.mod_inverse(&key.n) {
+            break (s, s_inv);
+        }
+    };
+    let blinded = (&s.mod_exp(&key.e, &key.n) * ciphertext).modulo(&key.n);
+    let plaintext = oracle.decrypt(&blinded)?;
+    Some((&plaintext * &s_inv).modulo(&key.n))
+}
+
+pub struct RsaParityOracle {
+    pair: RsaKeyPair,
+}
+
+impl RsaParityOracle {
+    pub fn new(pair: RsaKeyPair) -> Self {
+        RsaParityOracle { pair }
+    }
+
+    pub fn generate(bits: usize) -> Self {
+        RsaParityOracle::new(RsaKeyPair::generate(bits, E65537))
+    }
+
+    pub fn public_key(&self) -> &RsaPublicKey {
+        &self.pair.public
+    }
+
+    pub fn is_even(&self, ciphertext: &BigInt) -> bool {
+        self.pair
+            .private
+            .decrypt(ciphertext)
+            .map(|m| !m.is_odd())
+            .unwrap_or(false)
+    }
+}
+
+pub fn parity_oracle_attack<F: FnMut(&Bytes)>(
+    oracle: &RsaParityOracle,
+    ciphertext: &BigInt,
+    mut progress: F,
+) -> BigInt {
+    let key = oracle.public_key();
+    let multiplier = BigInt::from_u64(2).mod_exp(&key.e, &key.n);
+    let mut c = ciphertext.clone();
+    // plaintext lies in [a * n / 2^i, (a + 1) * n / 2^i)
+    let mut a = BigInt::zero();
+    let mut denominator = BigInt::one();
+    for _ in 0..key.n.bits() {
+        c = (&c * &multiplier).modulo(&key.n);
+        a = &a << 1;
+        denominator = &denominator << 1;
+        if !oracle.is_even(&c) {
+            a = &a + &BigInt::one();
+        }
+        progress(&(&(&(&a + &BigInt::one()) * &key.n) / &denominator).to_bytes());
+    }
+    let low = &a * &key.n;
+    let m = &low / &denominator;
+    if (&m * &denominator) == low {
+        m
+    } else {
+        &m + &BigInt::one()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaddingCheck {
+    Prefix,
+    PrefixAndSeparator,
+}
+
+pub struct Pkcs1PaddingOracle {
+    pair: RsaKeyPair,
+    check: PaddingCheck,
+}
+
+impl Pkcs1PaddingOracle {
+    pub fn new(pair: RsaKeyPair, check: PaddingCheck) -> Self {
+        Pkcs1PaddingOracle { pair, check }
+    }
+
+    pub fn generate(bits: usize, check: PaddingCheck) -> Self {
+        Pkcs1PaddingOracle::new(RsaKeyPair::generate(bits, E65537), check)
+    }
+
+    pub fn public_key(&self) -> &RsaPublicKey {
+        &self.pair.public
+    }
+
+    pub fn is_conforming(&self, ciphertext: &BigInt) -> bool {
+        let em = match self
+            .pair
+            .private
+            .decrypt(ciphertext)
+            .and_then(|m| i2osp(&m, self.pair.private.size()))
+        {
+            Ok(em) => em,
+            Err(_) => return false,
+        };
+        match self.check {
+            PaddingCheck::Prefix => em.0[..2] == [0x00, 0x02],
+            PaddingCheck::PrefixAndSeparator => pkcs1_unpad(&em).is_ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BleichenbacherResult {
+    pub plaintext: Bytes,
+    pub queries: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryBudgetExhausted {
+    pub queries: usize,
+}
+
+fn ceil_div(a: &BigInt, b: &BigInt) -> BigInt {
+    let q = a / b;
+    if &(&q * b) < a {
+        &q + &BigInt::one()
+    } else {
+        q
+    }
+}
+
+fn floor_div(a: &BigInt, b: &BigInt) -> BigInt {
+    let q = a / b;
+    if &(&q * b) > a {
+        &q - &BigInt::one()
+    } else {
+        q
+    }
+}
+
+fn merge_intervals(mut intervals: Vec<(BigInt, BigInt)>) -> Vec<(BigInt, BigInt)> {
+    intervals.sort();
+    let mut merged: Vec<(BigInt, BigInt)> = Vec::with_capacity(intervals.len());
+    for (a, b) in intervals {
+        match merged.last_mut() {
+            Some(last) if a <= &last.1 + &BigInt::one() => {
+                if b > last.1 {
+                    last.1 = b;
+                }
+            }
+            _ => merged.push((a, b)),
+        }
+    }
+    merged
+}
+
+pub fn bleichenbacher_attack(
+    oracle: &Pkcs1PaddingOracle,
+    ciphertext: &BigInt,
+    budget: Option<usize>,
+) -> Result<BleichenbacherResult, QueryBudgetExhausted> {
+    let key = oracle.public_key();
+    let n = &key.n;
+    let one = BigInt::one();
+    let big_b = &one << (8 * (key.size() as i32 - 2));
+    let (b2, b3) = (&big_b << 1, &(&big_b << 1) + &big_b);
+    let mut queries = 0;
+    let mut query = |c: &BigInt, s: &BigInt| {
+        if budget.is_some_and(|budget| queries >= budget) {
+            return Err(QueryBudgetExhausted { queries });
+        }
+        queries += 1;
+        Ok(oracle.is_conforming(&(c * &s.mod_exp(&key.e, n)).modulo(n)))
+    };
+
+    // step 1: blind until the ciphertext is conforming
+    let mut s0 = one.clone();
+    while !query(ciphertext, &s0)? {
+        s0 = &BigInt::random_below(&(n - &one)) + &one;
+    }
+    let c0 = (ciphertext * &s0.mod_exp(&key.e, n)).modulo(n);
+    let mut intervals = vec![(b2.clone(), &b3 - &one)];
+    let mut s = one.clone();
+
+    for i in 1.. {
+        s = if i == 1 {
+            // step 2a
+            let mut s = ceil_div(n, &b3);
+            while !query(&c0, &s)? {
+                s = &s + &one;
+            }
+            s
+        } else if intervals.len() > 1 {
+            // step 2b
+            let mut s = &s + &one;
+            while !query(&c0, &s)? {
+                s = &s + &one;
+            }
+            s
+        } else {
+            // step 2c
+            let (a, b) = &intervals[0];
+            let mut r = ceil_div(&((&(b * &s) - &b2) << 1), n);
+            'search: loop {
+                let low = ceil_div(&(&b2 + &(&r * n)), b);
+                let high = floor_div(&(&(&b3 - &one) + &(&r * n)), a);
+                let mut s = low;
+                while s <= high {
+                    if query(&c0, &s)? {
+                        break 'search s;
+                    }
+                    s = &s + &one;
+                }
+                r = &r + &one;
+            }
+        };
+
+        // step 3
+        let mut next = vec![];
+        for (a, b) in &intervals {
+            let mut r = ceil_div(&(&(&(a * &s) - &b3) + &one), n);
+            let r_max = floor_div(&(&(b * &s) - &b2), n);
+            while r <= r_max {
+                let low = ceil_div(&(&b2 + &(&r * n)), &s);
+                let high = floor_div(&(&(&b3 - &one) + &(&r * n)), &s);
+                let low = if &low > a { low } else { a.clone() };
+                let high = if &high < b { high } else { b.clone() };
+                if low <= high {
+                    next.push((low, high));
+                }
+                r = &r + &one;
+            }
+        }
+        intervals = merge_intervals(next);
+
+        // step 4
+        if intervals.len() == 1 && intervals[0].0 == intervals[0].1 {
+            break;
+        }
+    }
+    let m = (&intervals[0].0 * &s0.mod_inverse(n).unwrap()).modulo(n);
+    Ok(BleichenbacherResult {
+        plaintext: i2osp(&m, key.size()).unwrap(),
+        queries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::base64::B64;
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::hex::Hex;
+    use crate::mt19937::Mt19937;
+    use crate::oracles::{
+        bleichenbacher_attack, cookie_bitflip_attack, parity_oracle_attack, recover_iv_key,
+        recover_unpadded_rsa, BlockMode, CookieOracle, IvKeyOracle, PaddingCheck,
+        Pkcs1PaddingOracle, QueryBudgetExhausted, RsaDecryptOracle, RsaParityOracle,
+        UnpaddedRsaOracle,
+    };
+    use crate::rsa::{os2ip, pkcs1_unpad, RsaKeyPair, E65537};
+
+    #[test]
+    fn test_cookie_quoting() {
+        let mut rng = Mt19937::seed(31);
+        [BlockMode::Cbc, BlockMode::Ctr].iter().for_each(|mode| {
+            let oracle = CookieOracle::with_rng(&mut rng, *mode);
+            assert!(!oracle.is_admin(&oracle.encrypt(";admin=true;")));
+        });
+    }
+
+    #[test]
+    fn test_cookie_bitflip_attack() {
+        let mut rng = Mt19937::seed(16);
+        [BlockMode::Cbc, BlockMode::Ctr].iter().for_each(|mode| {
+            let oracle = CookieOracle::with_rng(&mut rng, *mode);
+            let forged = cookie_bitflip_attack(&oracle, b";admin=true;");
+            assert!(oracle.is_admin(&forged));
+        });
+    }
+
+    #[test]
+    fn test_recover_iv_key() {
+        let oracle = IvKeyOracle::with_rng(&mut Mt19937::seed(27));
+        let message = "comment1=cooking%20MCs;userdata=hello;comment2=%20like%20a%20pound";
+        let ciphertext = oracle.encrypt(Bytes::from_string(message.into()));
+        assert!(oracle.decrypt(&ciphertext).is_ok());
+        let key = recover_iv_key(&oracle, &ciphertext).unwrap();
+        let plaintext = ciphertext.decrypt_CBC(&key.0, &key.0).into_string();
+        assert!(plaintext.starts_with(message));
+    }
+
+    #[test]
+    fn test_unpadded_rsa_recovery() {
+        let oracle = UnpaddedRsaOracle::generate(1024);
+        let message = os2ip(&Bytes::from_string(
+            "{time: 1356304276, social: '555-55-5555'}".to_string(),
+        ));
+        let ciphertext = oracle.public_key().encrypt(&message).unwrap();
+        assert_eq!(oracle.decrypt(&ciphertext), Some(message.clone()));
+        assert_eq!(oracle.decrypt(&ciphertext), None);
+        assert_eq!(recover_unpadded_rsa(&oracle, &ciphertext), Some(message));
+    }
+
+    #[test]
+    fn test_parity_oracle_attack() {
+        let oracle = RsaParityOracle::generate(1024);
+        let message = B64("VGhhdCdzIHdoeSBJIGZvdW5kIHlvdSBkb24ndCBwbGF5IGFyb3VuZCB3aXRoIHRoZSBGdW5reSBDb2xkIE1lZGluYQ==".to_string()).to_bytes();
+        let ciphertext = oracle.public_key().encrypt(&os2ip(&message)).unwrap();
+        let mut partials = vec![];
+        let recovered = parity_oracle_attack(&oracle, &ciphertext, |p| partials.push(p.clone()));
+        assert_eq!(recovered, os2ip(&message));
+        assert_eq!(partials.len(), 1024);
+        assert_eq!(partials.last(), Some(&message));
+
+        let even = Bytes(vec![0x42; 64]);
+        let ciphertext = oracle.public_key().encrypt(&os2ip(&even)).unwrap();
+        assert!(oracle.is_even(&ciphertext));
+        assert_eq!(
+            parity_oracle_attack(&oracle, &ciphertext, |_| {}),
+            os2ip(&even)
+        );
+    }
+
+    fn encrypt_kick_it(oracle: &Pkcs1PaddingOracle) -> (Bytes, BigInt) {
+        let message = Bytes::from_string("kick it, CC".to_string());
+        let ciphertext = os2ip(&oracle.public_key().encrypt_pkcs1(&message).unwrap());
+        assert!(oracle.is_conforming(&ciphertext));
+        (message, ciphertext)
+    }
+
+    fn run_bleichenbacher(bits: usize, check: PaddingCheck) -> usize {
+        let oracle = Pkcs1PaddingOracle::generate(bits, check);
+        let (message, ciphertext) = encrypt_kick_it(&oracle);
+        let result = bleichenbacher_attack(&oracle, &ciphertext, None).unwrap();
+        assert_eq!(pkcs1_unpad(&result.plaintext), Ok(message));
+        result.queries
+    }
+
+    #[test]
+    fn test_padding_check_strictness() {
+        let pair = RsaKeyPair::generate(256, E65537);
+        let no_separator = os2ip(&Bytes([vec![0x00, 0x02], vec![0xff; 30]].concat()));
+        let ciphertext = pair.public.encrypt(&no_separator).unwrap();
+        let lax = Pkcs1PaddingOracle::new(pair.clone(), PaddingCheck::Prefix);
+        let strict = Pkcs1PaddingOracle::new(pair, PaddingCheck::PrefixAndSeparator);
+        assert!(lax.is_conforming(&ciphertext));
+        assert!(!strict.is_conforming(&ciphertext));
+    }
+
+    #[test]
+    fn test_bleichenbacher_256() {
+        assert!(run_bleichenbacher(256, PaddingCheck::Prefix) > 0);
+    }
+
+    #[test]
+    fn test_bleichenbacher_768() {
+        assert!(run_bleichenbacher(768, PaddingCheck::Prefix) > 0);
+    }
+
+    #[test]
+    fn test_bleichenbacher_budget() {
+        let oracle = Pkcs1PaddingOracle::generate(256, PaddingCheck::Prefix);
+        let (_, ciphertext) = encrypt_kick_it(&oracle);
+        assert_eq!(
+            bleichenbacher_attack(&oracle, &ciphertext, Some(1)),
+            Err(QueryBudgetExhausted { queries: 1 })
+        );
+        assert_eq!(
+            bleichenbacher_attack(&oracle, &BigInt::from_u64(2), Some(10)),
+            Err(QueryBudgetExhausted { queries: 10 })
+        );
+    }
+
+    #[test]
+    fn test_bleichenbacher_strictness_budget() {
+        let p = BigInt::from_hex(&Hex("d1b72c0f41a89e536b0c7f2e93d4a593".into())).unwrap();
+        let q = BigInt::from_hex(&Hex("e4c983a15f620b7dc8e14a3672f91d13".into())).unwrap();
+        let pair = RsaKeyPair::from_primes(p, q, BigInt::from_u64(E65537)).unwrap();
+        let em = Bytes(
+            [
+                vec![0x00, 0x02],
+                vec![0x7a; 18],
+                vec![0x00],
+                b"kick it, CC".to_vec(),
+            ]
+            .concat(),
+        );
+        let ciphertext = pair.public.encrypt(&os2ip(&em)).unwrap();
+        let lax = Pkcs1PaddingOracle::new(pair.clone(), PaddingCheck::Prefix);
+        let strict = Pkcs1PaddingOracle::new(pair, PaddingCheck::PrefixAndSeparator);
+        let result = bleichenbacher_attack(&lax, &ciphertext, Some(20_000)).unwrap();
+        assert_eq!(result.plaintext, em);
+        assert_eq!(
+            bleichenbacher_attack(&strict, &ciphertext, Some(20_000)),
+            Err(QueryBudgetExhausted { queries: 20_000 })
+        );
+    }
+
+    #[test]
+    #[ignore = "needs around a million oracle queries"]
+    fn test_bleichenbacher_strict_oracle() {
+        let pair = RsaKeyPair::generate(256, E65537);
+        let lax = Pkcs1PaddingOracle::new(pair.clone(), PaddingCheck::Prefix);
+        let strict = Pkcs1PaddingOracle::new(pair, PaddingCheck::PrefixAndSeparator);
+        let (message, ciphertext) = encrypt_kick_it(&lax);
+        let run = |oracle: &Pkcs1PaddingOracle| {
+            let result = bleichenbacher_attack(oracle, &ciphertext, None).unwrap();
+            assert_eq!(pkcs1_unpad(&result.plaintext), Ok(message.clone()));
+            result.queries
+        };
+        assert!(run(&strict) > run(&lax));
+    }
+}
@@ -0,0 +1,566 @@
+use std::fmt;
+
+use crate::base64::B64;
+use crate::bignum::BigInt;
+use crate::bytes::Bytes;
+use crate::hash::Hash;
+use crate::hex::Hex;
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+
+pub const E3: u64 = 3;
+pub const E65537: u64 = 65537;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RsaError {
+    InvalidExponent,
+    InvalidPrimes,
+    MessageTooLarge,
+    OutputTooShort,
+    InvalidPadding,
+}
+
+impl fmt::Display for RsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            RsaError::InvalidExponent => "exponent is not invertible mod phi(n)",
+            RsaError::InvalidPrimes => "factors are not distinct primes",
+            RsaError::MessageTooLarge => "message representative out of range",
+            RsaError::OutputTooShort => "integer too large for requested length",
+            RsaError::InvalidPadding => "invalid PKCS#1 v1.5 padding",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for RsaError {}
+
+pub fn i2osp(x: &BigInt, len: usize) -> Result<Bytes, RsaError> {
+    if x.is_negative() || x.num_bytes() > len {
+        return Err(RsaError::OutputTooShort);
+    }
+    x.to_bytes_padded(len).ok_or(RsaError::OutputTooShort)
+}
+
+pub fn os2ip(b: &Bytes) -> BigInt {
+    BigInt::from_bytes(b)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    pub n: BigInt,
+    pub e: BigInt,
+}
+
+impl RsaPublicKey {
+    pub fn new(n: BigInt, e: BigInt) -> Result<Self, RsaError> {
+        if e <= BigInt::one() || e >= n || !e.is_odd() {
+            return Err(RsaError::InvalidExponent);
+        }
+        Ok(RsaPublicKey { n, e })
+    }
+
+    pub fn size(&self) -> usize {
+        self.n.num_bytes()
+    }
+
+    pub fn encrypt(&self, m: &BigInt) -> Result<BigInt, RsaError> {
+        if m.is_negative() || *m >= self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+        Ok(m.mod_exp(&self.e, &self.n))
+    }
+
+    pub fn encrypt_bytes(&self, m: &Bytes) -> Result<Bytes, RsaError> {
+        i2osp(&self.encrypt(&os2ip(m))?, self.size())
+    }
+
+    pub fn modulus_hex(&self) -> Hex {
+        self.n.to_hex()
+    }
+
+    pub fn modulus_b64(&self) -> B64 {
+        self.n.to_bytes().to_b64()
+    }
+}
+
+impl fmt::Display for RsaPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "e={} n={}", self.e, self.n.to_hex().0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPrivateKey {
+    pub n: BigInt,
+    pub d: BigInt,
+    p: BigInt,
+    q: BigInt,
+    dp: BigInt,
+    dq: BigInt,
+    qinv: BigInt,
+}
+
+impl RsaPrivateKey {
+    pub fn size(&self) -> usize {
+        self.n.num_bytes()
+    }
+
+    pub fn primes(&self) -> (&BigInt, &BigInt) {
+        (&self.p, &self.q)
+    }
+
+    pub fn decrypt(&self, c: &BigInt) -> Result<BigInt, RsaError> {
+        if c.is_negative() || *c >= self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+        let m1 = c.mod_exp(&self.dp, &self.p);
+        let m2 = c.mod_exp(&self.dq, &self.q);
+        let h = (&self.qinv * &(&m1 - &m2)).modulo(&self.p);
+        Ok(&m2 + &(&h * &self.q))
+    }
+
+    pub fn decrypt_without_crt(&self, c: &BigInt) -> Result<BigInt, RsaError> {
+        if c.is_negative() || *c >= self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+        Ok(c.mod_exp(&self.d, &self.n))
+    }
+
+    pub fn decrypt_bytes(&self, c: &Bytes) -> Result<Bytes, RsaError> {
+        i2osp(&self.decrypt(&os2ip(c))?, self.size())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RsaKeyPair {
+    pub public: RsaPublicKey,
+    pub private: RsaPrivateKey,
+}
+
+impl RsaKeyPair {
+    pub fn from_primes(p: BigInt, q: BigInt, e: BigInt) -> Result<Self, RsaError> {
+        if p == q || !p.is_probable_prime(20) || !q.is_probable_prime(20) {
+            return Err(RsaError::InvalidPrimes);
+        }
+        let one = BigInt::one();
+        let (p1, q1) = (&p - &one, &q - &one);
+        let n = &p * &q;
+        let public = RsaPublicKey::new(n.clone(), e)?;
+        let d = public
+            .e
+            .mod_inverse(&(&p1 * &q1))
+            .ok_or(RsaError::InvalidExponent)?;
+        let private = RsaPrivateKey {
+            dp: d.modulo(&p1),
+            dq: d.modulo(&q1),
+            qinv: q.mod_inverse(&p).ok_or(RsaError::InvalidPrimes)?,
+            n,
+            d,
+            p,
+            q,
+        };
+        Ok(RsaKeyPair { public, private })
+    }
+
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn generate(bits: usize, e: u64) -> Self {
+        assert!(bits >= 16 && bits % 2 == 0);
+        let e = BigInt::from_u64(e);
+        let prime = || loop {
+            let p = BigInt::random_prime(bits / 2);
+            if (&p - &BigInt::one()).gcd(&e) == BigInt::one() {
+                return p;
+            }
+        };
+        loop {
+            if let Ok(pair) = RsaKeyPair::from_primes(prime(), prime(), e.clone()) {
+                if pair.public.n.bits() == bits {
+                    return pair;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BroadcastError {
+    NotEnoughCiphertexts { needed: usize, got: usize },
+    MixedExponents,
+    SharedFactor { i: usize, j: usize, factor: BigInt },
+    InexactRoot,
+}
+
+pub fn hastad_broadcast(captures: &[(BigInt, RsaPublicKey)]) -> Result<BigInt, BroadcastError> {
+    let e = captures
+        .first()
+        .map(|(_, key)| key.e.clone())
+        .ok_or(BroadcastError::NotEnoughCiphertexts { needed: 1, got: 0 })?;
+    if captures.iter().any(|(_, key)| key.e != e) {
+        return Err(BroadcastError::MixedExponents);
+    }
+    let needed = e.to_u64().map_or(usize::MAX, |e| e as usize);
+    if captures.len() < needed {
+        return Err(BroadcastError::NotEnoughCiphertexts {
+            needed,
+            got: captures.len(),
+        });
+    }
+    let captures = &captures[..needed];
+    for i in 0..needed {
+        for j in i + 1..needed {
+            let factor = captures[i].1.n.gcd(&captures[j].1.n);
+            if factor != BigInt::one() {
+                return Err(BroadcastError::SharedFactor { i, j, factor });
+            }
+        }
+    }
+    let (residues, moduli): (Vec<BigInt>, Vec<BigInt>) = captures
+        .iter()
+        .map(|(c, key)| (c.clone(), key.n.clone()))
+        .unzip();
+    let combined = BigInt::crt(&residues, &moduli).ok_or(BroadcastError::InexactRoot)?;
+    let root = combined.nth_root(needed as u32);
+    if root.pow(needed as u32) == combined {
+        Ok(root)
+    } else {
+        Err(BroadcastError::InexactRoot)
+    }
+}
+
+pub trait DigestInfo: Hash {
+    const DIGEST_INFO: &'static [u8];
+}
+
+impl DigestInfo for Sha1 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+    ];
+}
+
+impl DigestInfo for Sha256 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+        0x05, 0x00, 0x04, 0x20,
+    ];
+}
+
+fn digest_info<H: DigestInfo>(message: &[u8]) -> Vec<u8> {
+    [H::DIGEST_INFO, &<H as Hash>::digest(message)].concat()
+}
+
+pub fn pkcs1_sig_encode<H: DigestInfo>(message: &[u8], len: usize) -> Result<Bytes, RsaError> {
+    let t = digest_info::<H>(message);
+    if len < t.len() + 11 {
+        return Err(RsaError::MessageTooLarge);
+    }
+    let mut em = vec![0x00, 0x01];
+    em.resize(len - t.len() - 1, 0xff);
+    em.push(0x00);
+    em.extend_from_slice(&t);
+    Ok(Bytes(em))
+}
+
+impl RsaPrivateKey {
+    pub fn sign<H: DigestInfo>(&self, message: &[u8]) -> Result<Bytes, RsaError> {
+        let em = pkcs1_sig_encode::<H>(message, self.size())?;
+        i2osp(&self.decrypt(&os2ip(&em))?, self.size())
+    }
+}
+
+impl RsaPublicKey {
+    fn open_signature(&self, signature: &Bytes) -> Option<Bytes> {
+        if signature.0.len() != self.size() {
+            return None;
+        }
+        let em = self.encrypt(&os2ip(signature)).ok()?;
+        i2osp(&em, self.size()).ok()
+    }
+
+    pub fn verify<H: DigestInfo>(&self, message: &[u8], signature: &Bytes) -> bool {
+        match (
+            self.open_signature(signature),
+            pkcs1_sig_encode::<H>(message, self.size()),
+        ) {
+            (Some(em), Ok(expected)) => em == expected,
+            _ => false,
+        }
+    }
+
+    pub fn verify_sloppy<H: DigestInfo>(&self, message: &[u8], signature: &Bytes) -> bool {
+        let em = match self.open_signature(signature) {
+            Some(em) => em.0,
+            None => return false,
+        };
+        if em[..2] != [0x00, 0x01] {
+            return false;
+        }
+        let ff = em[2..].iter().take_while(|b| **b == 0xff).count();
+        if ff == 0 || em.get(2 + ff) != Some(&0x00) {
+            return false;
+        }
+        em[3 + ff..].starts_with(&digest_info::<H>(message))
+    }
+}
+
+pub fn forge_pkcs1_signature<H: DigestInfo>(key: &RsaPublicKey, message: &[u8]) -> Option<Bytes> {
+    if key.e != BigInt::from_u64(E3) {
+        return None;
+    }
+    let len = key.size();
+    let prefix = [&[0x00, 0x01, 0xff, 0x00][..], &digest_info::<H>(message)].concat();
+    if prefix.len() >= len {
+        return None;
+    }
+    let mut low = prefix.clone();
+    low.resize(len, 0x00);
+    let mut high = prefix;
+    high.resize(len, 0xff);
+    let root = os2ip(&Bytes(high)).nth_root(3);
+    if root.pow(3) < os2ip(&Bytes(low)) || root >= key.n {
+        return None;
+    }
+    i2osp(&root, len).ok()
+}
+
+pub fn pkcs1_pad(message: &Bytes, len: usize) -> Result<Bytes, RsaError> {
+    if message.0.len() + 11 > len {
+        return Err(RsaError::MessageTooLarge);
+    }
+    let mut em = vec![0x00, 0x02];
+    while em.len() < len - message.0.len() - 1 {
+        em.extend(
+            Bytes::random(len - message.0.len() - 1 - em.len())
+                .0
+                .into_iter()
+                .filter(|b| *b != 0),
+        );
+    }
+    em.push(0x00);
+    em.extend_from_slice(&message.0);
+    Ok(Bytes(em))
+}
+
+pub fn pkcs1_unpad(em: &Bytes) -> Result<Bytes, RsaError> {
+    if em.0.len() < 11 || em.0[..2] != [0x00, 0x02] {
+        return Err(RsaError::InvalidPadding);
+    }
+    match em.0[2..].iter().position(|b| *b == 0) {
+        Some(i) if i >= 8 => Ok(Bytes(em.0[3 + i..].to_vec())),
+        _ => Err(RsaError::InvalidPadding),
+    }
+}
+
+impl RsaPublicKey {
+    pub fn encrypt_pkcs1(&self, message: &Bytes) -> Result<Bytes, RsaError> {
+        self.encrypt_bytes(&pkcs1_pad(message, self.size())?)
+    }
+}
+
+impl RsaPrivateKey {
+    pub fn decrypt_pkcs1(&self, ciphertext: &Bytes) -> Result<Bytes, RsaError> {
+        pkcs1_unpad(&self.decrypt_bytes(ciphertext)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Verifier;
+
+    use crate::rsa::{
+        forge_pkcs1_signature, hastad_broadcast, i2osp, os2ip, pkcs1_pad, pkcs1_unpad,
+        BroadcastError, RsaError, RsaKeyPair, RsaPublicKey, E3, E65537,
+    };
+
+    #[test]
+    fn test_rsa_small() {
+        let pair = RsaKeyPair::from_primes(
+            BigInt::from_u64(61),
+            BigInt::from_u64(53),
+            BigInt::from_u64(17),
+        )
+        .unwrap();
+        assert_eq!(pair.public.n, BigInt::from_u64(3233));
+        assert_eq!(pair.private.d, BigInt::from_u64(2753));
+        let c = pair.public.encrypt(&BigInt::from_u64(65)).unwrap();
+        assert_eq!(c, BigInt::from_u64(2790));
+        assert_eq!(pair.private.decrypt(&c).unwrap(), BigInt::from_u64(65));
+    }
+
+    #[test]
+    fn test_rsa_invalid_keys() {
+        let (p, q) = (BigInt::from_u64(61), BigInt::from_u64(67));
+        // 3 divides (61 - 1) so e = 3 has no inverse mod phi(n)
+        assert_eq!(
+            RsaKeyPair::from_primes(p.clone(), q.clone(), BigInt::from_u64(3)).unwrap_err(),
+            RsaError::InvalidExponent
+        );
+        assert_eq!(
+            RsaKeyPair::from_primes(p.clone(), p.clone(), BigInt::from_u64(7)).unwrap_err(),
+            RsaError::InvalidPrimes
+        );
+        assert_eq!(
+            RsaKeyPair::from_primes(p, BigInt::from_u64(65), BigInt::from_u64(7)).unwrap_err(),
+            RsaError::InvalidPrimes
+        );
+        assert!(RsaPublicKey::new(BigInt::from_u64(3233), BigInt::one()).is_err());
+    }
+
+    #[test]
+    fn test_rsa_roundtrip() {
+        for e in &[E3, E65537] {
+            let pair = RsaKeyPair::generate(1024, *e);
+            assert_eq!(pair.public.n.bits(), 1024);
+            let m = Bytes::from_string("hi mom".to_string());
+            let c = pair.public.encrypt_bytes(&m).unwrap();
+            assert_eq!(c.0.len(), 128);
+            assert_eq!(c.to_hex().0.len(), 256);
+            assert_eq!(
+                pair.public.modulus_b64().to_bytes(),
+                pair.public.n.to_bytes()
+            );
+            assert_eq!(
+                pair.public.to_string(),
+                format!("e={} n={}", e, pair.public.modulus_hex().0)
+            );
+            let d = pair.private.decrypt_bytes(&c).unwrap();
+            assert_eq!(os2ip(&d), os2ip(&m));
+            let c = os2ip(&c);
+            assert_eq!(
+                pair.private.decrypt(&c).unwrap(),
+                pair.private.decrypt_without_crt(&c).unwrap()
+            );
+            assert_eq!(
+                pair.public.encrypt(&pair.public.n),
+                Err(RsaError::MessageTooLarge)
+            );
+        }
+    }
+
+    #[test]
+    fn test_i2osp() {
+        let x = BigInt::from_u64(0x0102);
+        assert_eq!(i2osp(&x, 4).unwrap(), Bytes(vec![0, 0, 1, 2]));
+        assert_eq!(i2osp(&x, 1), Err(RsaError::OutputTooShort));
+        assert_eq!(os2ip(&Bytes(vec![0, 0, 1, 2])), x);
+        assert_eq!(i2osp(&BigInt::zero(), 2).unwrap(), Bytes(vec![0, 0]));
+    }
+
+    #[test]
+    fn test_hastad_broadcast() {
+        let m = os2ip(&Bytes::from_string("attack at dawn".to_string()));
+        for (e, bits) in &[(3, 512), (5, 256), (7, 256)] {
+            let captures = (0..*e)
+                .map(|_| {
+                    let key = RsaKeyPair::generate(*bits, *e as u64).public;
+                    (key.encrypt(&m).unwrap(), key)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(hastad_broadcast(&captures), Ok(m.clone()));
+            assert_eq!(
+                hastad_broadcast(&captures[1..]),
+                Err(BroadcastError::NotEnoughCiphertexts {
+                    needed: *e,
+                    got: *e - 1
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_hastad_shared_factor() {
+        let m = os2ip(&Bytes::from_string("attack at dawn".to_string()));
+        let e = BigInt::from_u64(E3);
+        let prime = || RsaKeyPair::generate(256, E3).private.primes().0.clone();
+        let (p, q, r, s) = (prime(), prime(), prime(), prime());
+        let captures = vec![(p.clone(), q), (r, s), (p.clone(), prime())]
+            .into_iter()
+            .map(|(a, b)| {
+                let key = RsaKeyPair::from_primes(a, b, e.clone()).unwrap().public;
+                (key.encrypt(&m).unwrap(), key)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hastad_broadcast(&captures),
+            Err(BroadcastError::SharedFactor {
+                i: 0,
+                j: 2,
+                factor: p
+            })
+        );
+    }
+
+    #[test]
+    fn test_pkcs1_signatures() {
+        let pair = RsaKeyPair::generate(1024, E65537);
+        let message = b"hi mom";
+        let sig = pair.private.sign::<Sha256>(message).unwrap();
+        assert!(pair.public.verify::<Sha256>(message, &sig));
+        assert!(pair.public.verify_sloppy::<Sha256>(message, &sig));
+        assert!(!pair.public.verify::<Sha1>(message, &sig));
+        assert!(!pair.public.verify::<Sha256>(b"hi dad", &sig));
+
+        let rsa =
+            Rsa::from_public_components(pair.public.n.clone().0, pair.public.e.clone().0).unwrap();
+        let pkey = PKey::from_rsa(rsa).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.update(message).unwrap();
+        assert!(verifier.verify(&sig.0).unwrap());
+
+        let sig = pair.private.sign::<Sha1>(message).unwrap();
+        assert!(pair.public.verify::<Sha1>(message, &sig));
+        let mut verifier = Verifier::new(MessageDigest::sha1(), &pkey).unwrap();
+        verifier.update(message).unwrap();
+        assert!(verifier.verify(&sig.0).unwrap());
+    }
+
+    #[test]
+    fn test_pkcs1_forgery() {
+        let message = b"hi mom";
+        let key = RsaKeyPair::generate(1024, E3).public;
+        let forged = forge_pkcs1_signature::<Sha1>(&key, message).unwrap();
+        assert!(key.verify_sloppy::<Sha1>(message, &forged));
+        assert!(!key.verify::<Sha1>(message, &forged));
+        assert!(!key.verify_sloppy::<Sha1>(b"hi dad", &forged));
+        assert_eq!(forge_pkcs1_signature::<Sha256>(&key, message), None);
+
+        let key = RsaKeyPair::generate(2048, E3).public;
+        let forged = forge_pkcs1_signature::<Sha256>(&key, message).unwrap();
+        assert!(key.verify_sloppy::<Sha256>(message, &forged));
+        assert!(!key.verify::<Sha256>(message, &forged));
+    }
+
+    #[test]
+    fn test_pkcs1_encryption() {
+        let pair = RsaKeyPair::generate(512, E65537);
+        let message = Bytes::from_string("kick it, CC".to_string());
+        let c = pair.public.encrypt_pkcs1(&message).unwrap();
+        assert_eq!(pair.private.decrypt_pkcs1(&c).unwrap(), message);
+        assert_eq!(
+            pair.private
+                .decrypt_pkcs1(&pair.public.encrypt_bytes(&message).unwrap()),
+            Err(RsaError::InvalidPadding)
+        );
+
+        let em = pkcs1_pad(&message, 64).unwrap();
+        assert_eq!(em.0.len(), 64);
+        assert_eq!(em.0[..2], [0x00, 0x02]);
+        assert!(em.0[2..52].iter().all(|b| *b != 0));
+        assert_eq!(em.0[52], 0x00);
+        assert_eq!(pkcs1_unpad(&em), Ok(message));
+        assert_eq!(
+            pkcs1_pad(&Bytes(vec![0; 54]), 64),
+            Err(RsaError::MessageTooLarge)
+        );
+        let mut short_ps = vec![0x00, 0x02, 1, 2, 3, 0x00];
+        short_ps.resize(32, 0x41);
+        assert_eq!(pkcs1_unpad(&Bytes(short_ps)), Err(RsaError::InvalidPadding));
+    }
+}
@@ -0,0 +1,341 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::bytes::Bytes;
+use crate::hex::Hex;
+use crate::mac::{insecure_compare, Hmac};
+use crate::sha1::Sha1;
+
+fn parse_query(target: &str) -> Option<(String, Vec<u8>)> {
+    let (path, query) = target.split_at(target.find('?')?);
+    if path != "/test" {
+        return None;
+    }
+    let (mut file, mut signature) = (None, None);
+    for pair in query[1..].split('&') {
+        let mut kv = pair.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some("file"), Some(v)) => file = Some(v.to_string()),
+            (Some("signature"), Some(v)) => signature = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    Some((file?, Hex::parse(&signature?)?.to_bytes().0))
+}
+
+fn handle_connection(stream: TcpStream, key: &[u8], delay: Duration, mac_len: usize) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        match reader.read_line(&mut request_line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        loop {
+            let mut header = String::new();
+            match reader.read_line(&mut header) {
+                Ok(0) | Err(_) => return,
+                Ok(_) if header == "\r\n" || header == "\n" => break,
+                Ok(_) => {}
+            }
+        }
+        let valid = request_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(parse_query)
+            .map(|(file, signature)| {
+                let mac = Hmac::<Sha1>::mac(key, file.as_bytes());
+                insecure_compare(&mac[..mac_len], &signature, delay)
+            })
+            .unwrap_or(false);
+        let status = if valid {
+            "200 OK"
+        } else {
+            "500 Internal Server Error"
+        };
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+pub struct HmacServer {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl HmacServer {
+    pub fn start(key: Bytes, delay: Duration, mac_len: usize) -> io::Result<Self> {
+        assert!(mac_len > 0 && mac_len <= 20);
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let key = Arc::new(key);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let key = key.clone();
+                    stream.set_nodelay(true).ok();
+                    thread::spawn(move || handle_connection(stream, &key.0, delay, mac_len));
+                }
+            }
+        });
+        Ok(HmacServer {
+            addr,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for HmacServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        TcpStream::connect(self.addr).ok();
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+pub struct OracleClient {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl OracleClient {
+    pub fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(OracleClient {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        })
+    }
+
+    pub fn request(&mut self, file: &str, signature: &[u8]) -> io::Result<(u16, Duration)> {
+        let request = format!(
+            "GET /test?file={}&signature={} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            file,
+            Bytes(signature.to_vec()).to_hex().0
+        );
+        let start = Instant::now();
+        self.writer.write_all(request.as_bytes())?;
+        let mut status_line = String::new();
+        self.reader.read_line(&mut status_line)?;
+        let elapsed = start.elapsed();
+        loop {
+            let mut header = String::new();
+            if self.reader.read_line(&mut header)? == 0 || header == "\r\n" {
+                break;
+            }
+        }
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad status line"))?;
+        Ok((status, elapsed))
+    }
+}
+
+fn lower_quartile(samples: &mut [Duration]) -> Duration {
+    samples.sort();
+    samples[samples.len() / 4]
+}
+
+fn rank_candidates(
+    client: &mut OracleClient,
+    file: &str,
+    known: &[u8],
+    mac_len: usize,
+    candidates: &[u8],
+    rounds: usize,
+) -> io::Result<Vec<(u8, Duration)>> {
+    let mut timings = vec![Vec::with_capacity(rounds); candidates.len()];
+    let mut signature = known.to_vec();
+    signature.resize(mac_len, 0);
+    for _ in 0..rounds {
+        for (i, c) in candidates.iter().enumerate() {
+            signature[known.len()] = *c;
+            timings[i].push(client.request(file, &signature)?.1);
+        }
+    }
+    let mut ranked = candidates
+        .iter()
+        .zip(timings.iter_mut())
+        .map(|(c, t)| (*c, lower_quartile(t)))
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|r| std::cmp::Reverse(r.1));
+    Ok(ranked)
+}
+
+fn timing_margin(
+    client: &mut OracleClient,
+    file: &str,
+    known: &[u8],
+    mac_len: usize,
+    candidate: u8,
+    rounds: usize,
+) -> io::Result<Duration> {
+    let ranked = rank_candidates(
+        client,
+        file,
+        known,
+        mac_len,
+        &[candidate, !candidate],
+        rounds,
+    )?;
+    if ranked[0].0 == candidate {
+        Ok(ranked[0].1 - ranked[1].1)
+    } else {
+        Ok(Duration::from_secs(0))
+    }
+}
+
+fn recover_with_rounds(
+    client: &mut OracleClient,
+    file: &str,
+    mac_len: usize,
+    rounds: usize,
+) -> io::Result<Option<Vec<u8>>> {
+    let all = (0..=255).collect::<Vec<u8>>();
+    let mut known = Vec::with_capacity(mac_len);
+    let mut margins: Vec<Duration> = Vec::with_capacity(mac_len);
+    let mut failures = 0;
+    for _ in 0..mac_len * 4 {
+        if known.len() == mac_len - 1 {
+            break;
+        }
+        let rounds = rounds << failures;
+        let ranked = rank_candidates(client, file, &known, mac_len, &all, rounds)?;
+        let finalists = ranked.iter().take(8).map(|r| r.0).collect::<Vec<u8>>();
+        let best = rank_candidates(client, file, &known, mac_len, &finalists, rounds * 4)?[0].0;
+        let margin = timing_margin(client, file, &known, mac_len, best, rounds * 8)?;
+        let mut sorted = margins.clone();
+        sorted.sort();
+        let threshold = sorted
+            .get(sorted.len() / 2)
+            .map_or(Duration::from_secs(0), |m| *m / 2);
+        if margin > threshold {
+            known.push(best);
+            margins.push(margin);
+            failures = 0;
+        } else if failures == 2 {
+            known.pop();
+            margins.pop();
+            failures = 0;
+        } else {
+            failures += 1;
+        }
+    }
+    if known.len() < mac_len - 1 {
+        return Ok(None);
+    }
+    let mut signature = known;
+    signature.push(0);
+    for c in 0..=255 {
+        signature[mac_len - 1] = c;
+        if client.request(file, &signature)?.0 == 200 {
+            return Ok(Some(signature));
+        }
+    }
+    Ok(None)
+}
+
+pub fn recover_hmac_timing(
+    addr: SocketAddr,
+    file: &str,
+    mac_len: usize,
+    rounds: usize,
+) -> io::Result<Option<Vec<u8>>> {
+    if mac_len == 0 || mac_len > 20 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mac_len must be between 1 and 20",
+        ));
+    }
+    let mut client = OracleClient::connect(addr)?;
+    for attempt in 0..3 {
+        if let Some(mac) = recover_with_rounds(&mut client, file, mac_len, rounds << attempt)? {
+            return Ok(Some(mac));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+
+    use crate::bytes::Bytes;
+    use crate::mac::Hmac;
+    use crate::server::{recover_hmac_timing, HmacServer, OracleClient};
+    use crate::sha1::Sha1;
+
+    #[test]
+    fn test_hmac_server() {
+        let key = Bytes::random(16);
+        let mac = Hmac::<Sha1>::mac(&key.0, b"foo");
+        let server = HmacServer::start(key, Duration::from_millis(0), 20).unwrap();
+        let mut client = OracleClient::connect(server.addr()).unwrap();
+        assert_eq!(client.request("foo", &mac).unwrap().0, 200);
+        assert_eq!(client.request("bar", &mac).unwrap().0, 500);
+        assert_eq!(client.request("foo", &mac[..19]).unwrap().0, 500);
+    }
+
+    #[test]
+    fn test_timing_attack_5ms() {
+        let key = Bytes::random(16);
+        let mac = Hmac::<Sha1>::mac(&key.0, b"foo");
+        let server = HmacServer::start(key, Duration::from_millis(5), 3).unwrap();
+        let recovered = recover_hmac_timing(server.addr(), "foo", 3, 1).unwrap();
+        assert_eq!(recovered, Some(mac[..3].to_vec()));
+    }
+
+    #[test]
+    fn test_timing_attack_5us() {
+        let key = Bytes::random(16);
+        let mac = Hmac::<Sha1>::mac(&key.0, b"foo");
+        let server = HmacServer::start(key, Duration::from_micros(5), 5).unwrap();
+        let recovered = recover_hmac_timing(server.addr(), "foo", 5, 15).unwrap();
+        assert_eq!(recovered, Some(mac[..5].to_vec()));
+    }
+
+    #[test]
+    fn test_timing_attack_5us_full_mac() {
+        let key = Bytes::random(16);
+        let mac = Hmac::<Sha1>::mac(&key.0, b"foo");
+        let server = HmacServer::start(key, Duration::from_micros(5), 20).unwrap();
+        let recovered = recover_hmac_timing(server.addr(), "foo", 20, 15).unwrap();
+        assert_eq!(recovered, Some(mac));
+    }
+
+    #[test]
+    fn test_timing_attack_rejects_bad_mac_len() {
+        let server = HmacServer::start(Bytes::random(16), Duration::from_millis(0), 20).unwrap();
+        [0, 21].iter().for_each(|len| {
+            let err = recover_hmac_timing(server.addr(), "foo", *len, 1).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+}
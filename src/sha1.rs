@@ -0,0 +1,192 @@
+use crate::hash::{buffered_update, md_padding, Endian, Hash};
+
+const H0: [u32; 5] = [
+    0x6745_2301,
+    0xefcd_ab89,
+    0x98ba_dcfe,
+    0x1032_5476,
+    0xc3d2_e1f0,
+];
+
+fn compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for i in 0..16 {
+        w[i] = u32::from_be_bytes([
+            block[4 * i],
+            block[4 * i + 1],
+            block[4 * i + 2],
+            block[4 * i + 3],
+        ]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, wi) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+            _ => (b ^ c ^ d, 0xca62_c1d6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*wi);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
+}
+
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: Vec<u8>,
+    len: u64,
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Sha1::from_state(H0, 0)
+    }
+
+    pub fn from_state(state: [u32; 5], len: u64) -> Self {
+        Sha1 {
+            state,
+            buffer: Vec::with_capacity(64),
+            len,
+        }
+    }
+
+    pub fn from_digest(digest: &[u8; 20], len: u64) -> Self {
+        let mut state = [0; 5];
+        state
+            .iter_mut()
+            .zip(digest.chunks_exact(4))
+            .for_each(|(s, b)| *s = u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        Sha1::from_state(state, len)
+    }
+
+    pub fn state(&self) -> [u32; 5] {
+        self.state
+    }
+
+    pub fn processed_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+        let state = &mut self.state;
+        buffered_update(&mut self.buffer, data, |block| compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> [u8; 20] {
+        let pad = md_padding(self.len, Endian::Big);
+        self.update(&pad);
+        let mut res = [0; 20];
+        res.chunks_exact_mut(4)
+            .zip(self.state.iter())
+            .for_each(|(r, s)| r.copy_from_slice(&s.to_be_bytes()));
+        res
+    }
+
+    pub fn digest(data: &[u8]) -> [u8; 20] {
+        let mut h = Sha1::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1::new()
+    }
+}
+
+impl Hash for Sha1 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 20;
+    const ENDIAN: Endian = Endian::Big;
+
+    fn new() -> Self {
+        Sha1::new()
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Option<Self> {
+        if digest.len() != Self::OUTPUT_SIZE {
+            return None;
+        }
+        let mut d = [0; 20];
+        d.copy_from_slice(digest);
+        Some(Sha1::from_digest(&d, len))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha1::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Sha1::finalize(self).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::sha::sha1;
+
+    use crate::bytes::Bytes;
+    use crate::hash::{md_padding, Endian};
+    use crate::sha1::Sha1;
+
+    fn hex_digest(data: &[u8]) -> String {
+        Bytes(Sha1::digest(data).to_vec()).to_hex().0
+    }
+
+    #[test]
+    fn test_sha1_vectors() {
+        assert_eq!(hex_digest(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex_digest(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex_digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+        assert_eq!(
+            hex_digest(&[b'a'; 1_000_000]),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+        );
+    }
+
+    #[test]
+    fn test_sha1_incremental() {
+        let data = Bytes::random(1000);
+        (0..300).for_each(|len| {
+            let mut h = Sha1::new();
+            data.0[..len].chunks(7).for_each(|c| h.update(c));
+            assert_eq!(h.finalize(), sha1(&data.0[..len]));
+        });
+    }
+
+    #[test]
+    fn test_sha1_from_state() {
+        let prefix = b"YELLOW SUBMARINE";
+        let glued = [&prefix[..], &md_padding(prefix.len() as u64, Endian::Big)].concat();
+        let mut h = Sha1::from_digest(&Sha1::digest(prefix), glued.len() as u64);
+        assert_eq!(h.processed_len(), 64);
+        h.update(b"suffix");
+        assert_eq!(h.finalize(), sha1(&[&glued[..], b"suffix"].concat()));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::hash::{buffered_update, md_padding, Endian, Hash};
+
+const H0: [u32; 8] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4,
+    0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe,
+    0x9bdc_06a7, 0xc19b_f174, 0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f,
+    0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da, 0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7,
+    0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc,
+    0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85, 0xa2bf_e8a1, 0xa81a_664b,
+    0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070, 0x19a4_c116,
+    0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    w.iter_mut()
+        .zip(block.chunks_exact(4))
+        .for_each(|(w, b)| *w = u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (k, wi) in K.iter().zip(w.iter()) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(*wi);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+    state
+        .iter_mut()
+        .zip([a, b, c, d, e, f, g, h].iter())
+        .for_each(|(s, v)| *s = s.wrapping_add(*v));
+}
+
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    len: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Sha256::from_state(H0, 0)
+    }
+
+    pub fn from_state(state: [u32; 8], len: u64) -> Self {
+        Sha256 {
+            state,
+            buffer: Vec::with_capacity(64),
+            len,
+        }
+    }
+
+    pub fn from_digest(digest: &[u8; 32], len: u64) -> Self {
+        let mut state = [0; 8];
+        state
+            .iter_mut()
+            .zip(digest.chunks_exact(4))
+            .for_each(|(s, b)| *s = u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        Sha256::from_state(state, len)
+    }
+
+    pub fn state(&self) -> [u32; 8] {
+        self.state
+    }
+
+    pub fn processed_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+        let state = &mut self.state;
+        buffered_update(&mut self.buffer, data, |block| compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> [u8; 32] {
+        let pad = md_padding(self.len, Endian::Big);
+        self.update(&pad);
+        let mut res = [0; 32];
+        res.chunks_exact_mut(4)
+            .zip(self.state.iter())
+            .for_each(|(r, s)| r.copy_from_slice(&s.to_be_bytes()));
+        res
+    }
+
+    pub fn digest(data: &[u8]) -> [u8; 32] {
+        let mut h = Sha256::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256::new()
+    }
+}
+
+impl Hash for Sha256 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 32;
+    const ENDIAN: Endian = Endian::Big;
+
+    fn new() -> Self {
+        Sha256::new()
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Option<Self> {
+        if digest.len() != Self::OUTPUT_SIZE {
+            return None;
+        }
+        let mut d = [0; 32];
+        d.copy_from_slice(digest);
+        Some(Sha256::from_digest(&d, len))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha256::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Sha256::finalize(self).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::sha::sha256;
+
+    use crate::bytes::Bytes;
+    use crate::sha256::Sha256;
+
+    fn hex_digest(data: &[u8]) -> String {
+        Bytes(Sha256::digest(data).to_vec()).to_hex().0
+    }
+
+    #[test]
+    fn test_sha256_vectors() {
+        assert_eq!(
+            hex_digest(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex_digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex_digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_sha256_incremental() {
+        let data = Bytes::random(300);
+        (0..300).for_each(|len| {
+            let mut h = Sha256::new();
+            data.0[..len].chunks(7).for_each(|c| h.update(c));
+            assert_eq!(h.finalize(), sha256(&data.0[..len]));
+        });
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use rayon::prelude::*;
+
+use crate::bignum::BigInt;
+use crate::bytes::Bytes;
+use crate::channel::{Channel, TcpChannel, Wire};
+use crate::dh::DhGroup;
+use crate::hex::Hex;
+use crate::mac::{constant_time_eq, Hmac};
+use crate::sha256::Sha256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SrpMessage {
+    Hello { email: String, a: BigInt },
+    Challenge { salt: Bytes, b: BigInt },
+    SimpleChallenge { salt: Bytes, b: BigInt, u: BigInt },
+    Proof(Bytes),
+    Ok,
+    Fail,
+}
+
+fn hex_bytes(s: &str) -> Option<Bytes> {
+    Some(Hex::parse(s)?.to_bytes())
+}
+
+fn hex_bigint(s: &str) -> Option<BigInt> {
+    BigInt::from_hex(&Hex(s.into()))
+}
+
+impl Wire for SrpMessage {
+    fn encode(&self) -> String {
+        match self {
+            SrpMessage::Hello { email, a } => format!(
+                "HELLO {} {}",
+                Bytes::from_string(email.clone()).to_hex().0,
+                a.to_hex().0
+            ),
+            SrpMessage::Challenge { salt, b } => {
+                format!("CHALLENGE {} {}", salt.to_hex().0, b.to_hex().0)
+            }
+            SrpMessage::SimpleChallenge { salt, b, u } => format!(
+                "SIMPLE {} {} {}",
+                salt.to_hex().0,
+                b.to_hex().0,
+                u.to_hex().0
+            ),
+            SrpMessage::Proof(mac) => format!("PROOF {}", mac.to_hex().0),
+            SrpMessage::Ok => "OK".to_string(),
+            SrpMessage::Fail => "FAIL".to_string(),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let parts = line.split(' ').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            ["HELLO", email, a] => Some(SrpMessage::Hello {
+                email: String::from_utf8(hex_bytes(email)?.0).ok()?,
+                a: hex_bigint(a)?,
+            }),
+            ["CHALLENGE", salt, b] => Some(SrpMessage::Challenge {
+                salt: hex_bytes(salt)?,
+                b: hex_bigint(b)?,
+            }),
+            ["SIMPLE", salt, b, u] => Some(SrpMessage::SimpleChallenge {
+                salt: hex_bytes(salt)?,
+                b: hex_bigint(b)?,
+                u: hex_bigint(u)?,
+            }),
+            ["PROOF", mac] => Some(SrpMessage::Proof(hex_bytes(mac)?)),
+            ["OK"] => Some(SrpMessage::Ok),
+            ["FAIL"] => Some(SrpMessage::Fail),
+            _ => None,
+        }
+    }
+}
+
+fn unexpected(msg: &SrpMessage) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message {:?}", msg),
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct SrpParams {
+    pub group: DhGroup,
+    pub k: BigInt,
+}
+
+impl SrpParams {
+    pub fn new(group: DhGroup) -> Self {
+        let k = BigInt::from_bytes(&Bytes(
+            Sha256::digest(&[group.p.to_bytes().0, pad(&group.g, &group.p).0].concat()).to_vec(),
+        ));
+        SrpParams { group, k }
+    }
+
+    fn n(&self) -> &BigInt {
+        &self.group.p
+    }
+
+    fn g(&self) -> &BigInt {
+        &self.group.g
+    }
+}
+
+impl Default for SrpParams {
+    fn default() -> Self {
+        SrpParams::new(DhGroup::modp_1536())
+    }
+}
+
+fn pad(x: &BigInt, n: &BigInt) -> Bytes {
+    x.modulo(n).to_bytes_padded(n.num_bytes()).unwrap()
+}
+
+pub fn hash_to_int(data: &[u8]) -> BigInt {
+    BigInt::from_bytes(&Bytes(Sha256::digest(data).to_vec()))
+}
+
+pub fn private_key(salt: &Bytes, email: &str, password: &str) -> BigInt {
+    let inner = Sha256::digest(format!("{}:{}", email, password).as_bytes());
+    hash_to_int(&[&salt.0[..], &inner[..]].concat())
+}
+
+pub fn scrambler(params: &SrpParams, a: &BigInt, b: &BigInt) -> BigInt {
+    hash_to_int(&[pad(a, params.n()).0, pad(b, params.n()).0].concat())
+}
+
+fn nonzero_scrambler(u: BigInt) -> io::Result<BigInt> {
+    if u.is_zero() {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "scrambler u is zero",
+        ))
+    } else {
+        Ok(u)
+    }
+}
+
+pub fn session_proof(secret: &BigInt, salt: &Bytes) -> Bytes {
+    let key = Sha256::digest(&secret.to_bytes().0);
+    Bytes(Hmac::<Sha256>::mac(&key, &salt.0))
+}
+
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    pub salt: Bytes,
+    pub v: BigInt,
+}
+
+pub struct SrpServer {
+    params: SrpParams,
+    users: HashMap<String, Verifier>,
+    check_public_key: bool,
+}
+
+impl SrpServer {
+    pub fn new(params: SrpParams) -> Self {
+        SrpServer {
+            params,
+            users: HashMap::new(),
+            check_public_key: true,
+        }
+    }
+
+    pub fn set_public_key_check(&mut self, enabled: bool) {
+        self.check_public_key = enabled;
+    }
+
+    pub fn params(&self) -> &SrpParams {
+        &self.params
+    }
+
+    pub fn register(&mut self, email: &str, password: &str) {
+        let salt = Bytes::random(16);
+        let x = private_key(&salt, email, password);
+        let v = self.params.g().mod_exp(&x, self.params.n());
+        self.users.insert(email.to_string(), Verifier { salt, v });
+    }
+
+    pub fn serve<C: Channel<SrpMessage>>(&self, chan: &mut C) -> io::Result<bool> {
+        let (email, a) = match chan.recv()? {
+            SrpMessage::Hello { email, a } => (email, a),
+            msg => return Err(unexpected(&msg)),
+        };
+        let n = self.params.n();
+        let verifier = match self.users.get(&email) {
+            Some(v) if !self.check_public_key || !a.modulo(n).is_zero() => v,
+            _ => {
+                chan.send(SrpMessage::Fail)?;
+                return Ok(false);
+            }
+        };
+        let b = &BigInt::random_below(n) + &BigInt::one();
+        let big_b = (&(&self.params.k * &verifier.v) + &self.params.g().mod_exp(&b, n)).modulo(n);
+        let u = match nonzero_scrambler(scrambler(&self.params, &a, &big_b)) {
+            Ok(u) => u,
+            Err(_) => {
+                chan.send(SrpMessage::Fail)?;
+                return Ok(false);
+            }
+        };
+        chan.send(SrpMessage::Challenge {
+            salt: verifier.salt.clone(),
+            b: big_b.clone(),
+        })?;
+        let secret = (&a * &verifier.v.mod_exp(&u, n)).mod_exp(&b, n);
+        let expected = session_proof(&secret, &verifier.salt);
+        let ok = match chan.recv()? {
+            SrpMessage::Proof(mac) => constant_time_eq(&mac.0, &expected.0),
+            msg => return Err(unexpected(&msg)),
+        };
+        chan.send(if ok { SrpMessage::Ok } else { SrpMessage::Fail })?;
+        Ok(ok)
+    }
+
+    pub fn spawn_tcp(
+        server: Arc<SrpServer>,
+        connections: usize,
+    ) -> io::Result<(SocketAddr, JoinHandle<()>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming().take(connections).flatten() {
+                if let Ok(mut chan) = TcpChannel::new(stream) {
+                    server.serve(&mut chan).ok();
+                }
+            }
+        });
+        Ok((addr, handle))
+    }
+}
+
+pub fn srp_login<C: Channel<SrpMessage>>(
+    chan: &mut C,
+    params: &SrpParams,
+    email: &str,
+    password: &str,
+) -> io::Result<bool> {
+    let n = params.n();
+    let a = &BigInt::random_below(n) + &BigInt::one();
+    let big_a = params.g().mod_exp(&a, n);
+    chan.send(SrpMessage::Hello {
+        email: email.to_string(),
+        a: big_a.clone(),
+    })?;
+    let (salt, big_b) = match chan.recv()? {
+        SrpMessage::Challenge { salt, b } => (salt, b),
+        SrpMessage::Fail => return Ok(false),
+        msg => return Err(unexpected(&msg)),
+    };
+    if big_b.modulo(n).is_zero() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "server public key B is zero mod N",
+        ));
+    }
+    let u = nonzero_scrambler(scrambler(params, &big_a, &big_b))?;
+    let x = private_key(&salt, email, password);
+    let base = (&big_b - &(&params.k * &params.g().mod_exp(&x, n))).modulo(n);
+    let secret = base.mod_exp(&(&a + &(&u * &x)), n);
+    chan.send(SrpMessage::Proof(session_proof(&secret, &salt)))?;
+    match chan.recv()? {
+        SrpMessage::Ok => Ok(true),
+        SrpMessage::Fail => Ok(false),
+        msg => Err(unexpected(&msg)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZeroKeyOutcome {
+    pub a: BigInt,
+    pub accepted: bool,
+}
+
+pub fn zero_key_login<C: Channel<SrpMessage>>(
+    chan: &mut C,
+    email: &str,
+    a: &BigInt,
+) -> io::Result<bool> {
+    chan.send(SrpMessage::Hello {
+        email: email.to_string(),
+        a: a.clone(),
+    })?;
+    let salt = match chan.recv()? {
+        SrpMessage::Challenge { salt, .. } => salt,
+        SrpMessage::Fail => return Ok(false),
+        msg => return Err(unexpected(&msg)),
+    };
+    chan.send(SrpMessage::Proof(session_proof(&BigInt::zero(), &salt)))?;
+    match chan.recv()? {
+        SrpMessage::Ok => Ok(true),
+        SrpMessage::Fail => Ok(false),
+        msg => Err(unexpected(&msg)),
+    }
+}
+
+pub fn zero_key_attack<C, F>(
+    mut connect: F,
+    params: &SrpParams,
+    email: &str,
+) -> io::Result<Vec<ZeroKeyOutcome>>
+where
+    C: Channel<SrpMessage>,
+    F: FnMut() -> io::Result<C>,
+{
+    let n = params.n();
+    vec![BigInt::zero(), n.clone(), n * &BigInt::from_u64(2)]
+        .into_iter()
+        .map(|a| {
+            let accepted = zero_key_login(&mut connect()?, email, &a)?;
+            Ok(ZeroKeyOutcome { a, accepted })
+        })
+        .collect()
+}
+
+pub fn simple_srp_login<C: Channel<SrpMessage>>(
+    chan: &mut C,
+    params: &SrpParams,
+    email: &str,
+    password: &str,
+) -> io::Result<bool> {
+    let n = params.n();
+    let a = &BigInt::random_below(n) + &BigInt::one();
+    chan.send(SrpMessage::Hello {
+        email: email.to_string(),
+        a: params.g().mod_exp(&a, n),
+    })?;
+    let (salt, big_b, u) = match chan.recv()? {
+        SrpMessage::SimpleChallenge { salt, b, u } => (salt, b, u),
+        SrpMessage::Fail => return Ok(false),
+        msg => return Err(unexpected(&msg)),
+    };
+    let x = private_key(&salt, email, password);
+    let secret = big_b.mod_exp(&(&a + &(&u * &x)), n);
+    chan.send(SrpMessage::Proof(session_proof(&secret, &salt)))?;
+    match chan.recv()? {
+        SrpMessage::Ok => Ok(true),
+        SrpMessage::Fail => Ok(false),
+        msg => Err(unexpected(&msg)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedProof {
+    pub email: String,
+    pub a: BigInt,
+    pub salt: Bytes,
+    pub mac: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrackedPassword {
+    pub email: String,
+    pub password: String,
+}
+
+pub struct MaliciousSrpServer {
+    params: SrpParams,
+}
+
+impl MaliciousSrpServer {
+    pub fn new(params: SrpParams) -> Self {
+        MaliciousSrpServer { params }
+    }
+
+    pub fn capture<C: Channel<SrpMessage>>(&self, chan: &mut C) -> io::Result<CapturedProof> {
+        let (email, a) = match chan.recv()? {
+            SrpMessage::Hello { email, a } => (email, a),
+            msg => return Err(unexpected(&msg)),
+        };
+        let salt = Bytes::random(16);
+        chan.send(SrpMessage::SimpleChallenge {
+            salt: salt.clone(),
+            b: self.params.g().clone(),
+            u: BigInt::one(),
+        })?;
+        let mac = match chan.recv()? {
+            SrpMessage::Proof(mac) => mac,
+            msg => return Err(unexpected(&msg)),
+        };
+        chan.send(SrpMessage::Ok)?;
+        Ok(CapturedProof {
+            email,
+            a,
+            salt,
+            mac,
+        })
+    }
+
+    fn matches(&self, captured: &CapturedProof, password: &str) -> bool {
+        let n = self.params.n();
+        let x = private_key(&captured.salt, &captured.email, password);
+        let secret = (&captured.a * &self.params.g().mod_exp(&x, n)).modulo(n);
+        constant_time_eq(&session_proof(&secret, &captured.salt).0, &captured.mac.0)
+    }
+
+    pub fn crack(&self, captured: &CapturedProof, words: &[String]) -> Option<CrackedPassword> {
+        words
+            .iter()
+            .find(|w| self.matches(captured, w))
+            .map(|w| CrackedPassword {
+                email: captured.email.clone(),
+                password: w.clone(),
+            })
+    }
+
+    pub fn crack_par(&self, captured: &CapturedProof, words: &[String]) -> Option<CrackedPassword> {
+        words
+            .par_iter()
+            .find_any(|w| self.matches(captured, w))
+            .map(|w| CrackedPassword {
+                email: captured.email.clone(),
+                password: w.clone(),
+            })
+    }
+}
+
+pub fn load_wordlist<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|l| l.as_ref().map(|l| !l.trim().is_empty()).unwrap_or(true))
+        .map(|l| l.map(|l| l.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::bignum::BigInt;
+    use crate::bytes::Bytes;
+    use crate::channel::{pair, Channel, TcpChannel, Wire};
+    use crate::srp::{
+        load_wordlist, nonzero_scrambler, simple_srp_login, srp_login, zero_key_attack,
+        MaliciousSrpServer, SrpMessage, SrpParams, SrpServer, ZeroKeyOutcome,
+    };
+
+    fn server() -> Arc<SrpServer> {
+        let mut server = SrpServer::new(SrpParams::default());
+        server.register("alice@example.com", "correct horse battery staple");
+        Arc::new(server)
+    }
+
+    fn login_in_process(server: &Arc<SrpServer>, email: &str, password: &str) -> bool {
+        let (mut client, mut server_end) = pair();
+        let params = server.params().clone();
+        let server = server.clone();
+        let handle = thread::spawn(move || server.serve(&mut server_end).unwrap());
+        let ok = srp_login(&mut client, &params, email, password).unwrap();
+        assert_eq!(handle.join().unwrap(), ok);
+        ok
+    }
+
+    #[test]
+    fn test_srp_in_process() {
+        let server = server();
+        assert!(login_in_process(
+            &server,
+            "alice@example.com",
+            "correct horse battery staple"
+        ));
+        assert!(!login_in_process(&server, "alice@example.com", "hunter2"));
+        assert!(!login_in_process(
+            &server,
+            "bob@example.com",
+            "correct horse battery staple"
+        ));
+    }
+
+    #[test]
+    fn test_srp_tcp() {
+        let server = server();
+        let params = server.params().clone();
+        let (addr, handle) = SrpServer::spawn_tcp(server, 2).unwrap();
+        let mut chan = TcpChannel::connect(addr).unwrap();
+        assert!(srp_login(
+            &mut chan,
+            &params,
+            "alice@example.com",
+            "correct horse battery staple"
+        )
+        .unwrap());
+        let mut chan = TcpChannel::connect(addr).unwrap();
+        assert!(!srp_login(&mut chan, &params, "alice@example.com", "hunter2").unwrap());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_srp_client_aborts() {
+        let params = SrpParams::default();
+        let n = &params.group.p;
+        [BigInt::zero(), n.clone(), n * &BigInt::from_u64(2)]
+            .iter()
+            .for_each(|b| {
+                let (mut client, mut server_end) = pair();
+                let b = b.clone();
+                let handle = thread::spawn(move || {
+                    server_end.recv().unwrap();
+                    server_end
+                        .send(SrpMessage::Challenge {
+                            salt: Bytes::random(16),
+                            b,
+                        })
+                        .unwrap();
+                });
+                let err =
+                    srp_login(&mut client, &params, "alice@example.com", "hunter2").unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                assert_eq!(err.to_string(), "server public key B is zero mod N");
+                handle.join().unwrap();
+            });
+        let err = nonzero_scrambler(BigInt::zero()).unwrap_err();
+        assert_eq!(err.to_string(), "scrambler u is zero");
+        assert_eq!(nonzero_scrambler(BigInt::one()).unwrap(), BigInt::one());
+    }
+
+    #[test]
+    fn test_srp_wire() {
+        let messages = vec![
+            SrpMessage::Hello {
+                email: "alice@example.com".into(),
+                a: BigInt::from_u64(0xdead_beef),
+            },
+            SrpMessage::Hello {
+                email: "zoë@bücher.example".into(),
+                a: BigInt::one(),
+            },
+            SrpMessage::Challenge {
+                salt: Bytes::random(16),
+                b: BigInt::zero(),
+            },
+            SrpMessage::SimpleChallenge {
+                salt: Bytes(vec![]),
+                b: BigInt::from_u64(2),
+                u: BigInt::one(),
+            },
+            SrpMessage::Proof(Bytes::random(32)),
+            SrpMessage::Ok,
+            SrpMessage::Fail,
+        ];
+        messages.into_iter().for_each(|msg| {
+            assert_eq!(SrpMessage::decode(&msg.encode()), Some(msg));
+        });
+        assert_eq!(SrpMessage::decode("PROOF zz"), None);
+        assert_eq!(SrpMessage::decode("HELLO"), None);
+        assert_eq!(SrpMessage::decode("HELLO c3 01"), None);
+        assert_eq!(SrpMessage::decode("PROOF abc"), None);
+    }
+
+    #[test]
+    fn test_srp_zero_key() {
+        for check in [true, false] {
+            let mut server = SrpServer::new(SrpParams::default());
+            server.register("alice@example.com", "correct horse battery staple");
+            server.set_public_key_check(check);
+            let params = server.params().clone();
+            let (addr, handle) = SrpServer::spawn_tcp(Arc::new(server), 3).unwrap();
+            let outcomes =
+                zero_key_attack(|| TcpChannel::connect(addr), &params, "alice@example.com")
+                    .unwrap();
+            handle.join().unwrap();
+            let n = &params.group.p;
+            assert_eq!(
+                outcomes,
+                vec![BigInt::zero(), n.clone(), n * &BigInt::from_u64(2)]
+                    .into_iter()
+                    .map(|a| ZeroKeyOutcome {
+                        a,
+                        accepted: !check
+                    })
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_simple_srp_dictionary() {
+        let words = load_wordlist("resources/chal38").unwrap();
+        let password = words[words.len() * 2 / 3].clone();
+        let mitm = Arc::new(MaliciousSrpServer::new(SrpParams::default()));
+        let (mut client, mut server_end) = pair();
+        let server = mitm.clone();
+        let handle = thread::spawn(move || server.capture(&mut server_end).unwrap());
+        let params = SrpParams::default();
+        assert!(simple_srp_login(&mut client, &params, "alice@example.com", &password).unwrap());
+        let captured = handle.join().unwrap();
+        assert_eq!(captured.email, "alice@example.com");
+
+        let cracked = mitm.crack(&captured, &words).unwrap();
+        assert_eq!(cracked.email, "alice@example.com");
+        assert_eq!(cracked.password, password);
+        assert_eq!(mitm.crack_par(&captured, &words), Some(cracked));
+        assert_eq!(mitm.crack(&captured, &words[..words.len() / 2]), None);
+    }
+}